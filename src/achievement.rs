@@ -1,5 +1,12 @@
+pub mod icon;
+pub mod localization;
+pub mod poller;
+pub mod view;
+
 use serde::{Deserialize, Serialize};
 
+use crate::util::null_as_default;
+
 /// Represents a Gamplo achievement.
 /// Used in responses from [`crate::Gamplo::get_achievements`].
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -8,14 +15,22 @@ pub struct Achievement {
     pub(crate) id: u32,
     pub(crate) key: String,
     pub(crate) title: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub(crate) description: String,
-    #[serde(rename = "icon")]
+    #[serde(rename = "icon", default, deserialize_with = "null_as_default")]
     pub(crate) icon_url: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub(crate) points: u32,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub(crate) hidden: bool,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub(crate) unlocked: bool,
-    #[serde(rename = "unlockedAt")]
-    pub(crate) unlocked_at: chrono::DateTime<chrono::Utc>,
+    /// `None` for locked achievements, for which the API returns `null` or omits the field.
+    #[serde(rename = "unlockedAt", default)]
+    pub(crate) unlocked_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Fields returned by the API that this version of the SDK does not know about.
+    #[serde(flatten)]
+    pub(crate) extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Achievement {
@@ -43,9 +58,14 @@ impl Achievement {
     pub fn unlocked(&self) -> bool {
         self.unlocked
     }
-    pub fn unlocked_at(&self) -> chrono::DateTime<chrono::Utc> {
+    /// When the achievement was unlocked, or `None` if it is still locked.
+    pub fn unlocked_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
         self.unlocked_at
     }
+    /// Fields returned by the API that are not modeled by this SDK.
+    pub fn extra(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.extra
+    }
 }
 
 /// A lighter version of [`Achievement`] used in the unlock response to avoid redundant fields.
@@ -55,10 +75,15 @@ impl Achievement {
 pub struct AchievementLite {
     pub(crate) key: String,
     pub(crate) title: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub(crate) description: String,
-    #[serde(rename = "icon")]
+    #[serde(rename = "icon", default, deserialize_with = "null_as_default")]
     pub(crate) icon_url: String,
+    #[serde(default, deserialize_with = "null_as_default")]
     pub(crate) points: u32,
+    /// Fields returned by the API that this version of the SDK does not know about.
+    #[serde(flatten)]
+    pub(crate) extra: serde_json::Map<String, serde_json::Value>,
 }
 impl AchievementLite {
     pub fn key(&self) -> &str {
@@ -76,6 +101,10 @@ impl AchievementLite {
     pub fn points(&self) -> u32 {
         self.points
     }
+    /// Fields returned by the API that are not modeled by this SDK.
+    pub fn extra(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.extra
+    }
 }
 /// Response from unlocking an achievement with
 /// [`crate::Gamplo::unlock_achievement`] and
@@ -0,0 +1,362 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use reqwest::{
+    StatusCode,
+    header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Gamplo,
+    achievement::{Achievement, AchievementLite},
+    error::GamploError,
+    storage::{MemoryStorage, Storage},
+};
+
+const STORAGE_PREFIX: &str = "icon:";
+
+/// A downloaded achievement icon.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Icon {
+    url: String,
+    bytes: Vec<u8>,
+    content_type: String,
+    dimensions: Option<(u32, u32)>,
+    etag: Option<String>,
+}
+impl Icon {
+    /// The URL the icon was downloaded from.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    /// The raw (still encoded) image bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    /// The MIME type of the image, e.g. `image/png`.
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+    /// The `(width, height)` of the image in pixels, if the format is recognized.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        self.dimensions
+    }
+    pub fn width(&self) -> Option<u32> {
+        self.dimensions.map(|(w, _)| w)
+    }
+    pub fn height(&self) -> Option<u32> {
+        self.dimensions.map(|(_, h)| h)
+    }
+    /// The `ETag` the server returned for the icon, used to revalidate the cached copy.
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    fn new(url: &str, bytes: Vec<u8>, content_type: Option<String>, etag: Option<String>) -> Self {
+        let content_type = content_type
+            .filter(|t| !t.is_empty() && t != "application/octet-stream")
+            .or_else(|| sniff_content_type(&bytes).map(str::to_string))
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        Self {
+            url: url.to_string(),
+            dimensions: sniff_dimensions(&bytes),
+            bytes,
+            content_type,
+            etag,
+        }
+    }
+}
+
+/// How an icon is kept in [`Storage`].
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredIcon {
+    content_type: String,
+    etag: Option<String>,
+    data: String,
+}
+
+/// Fetches achievement icons through the [`Gamplo`] client's HTTP client and caches them
+/// in memory and in a persistent [`Storage`], keyed by URL.
+///
+/// Icons found in storage are revalidated with their `ETag` the first time they are loaded in a session.
+/// If revalidation fails (e.g. while offline or on a server error), the stored copy is used.
+#[derive(Debug)]
+pub struct IconLoader<S = MemoryStorage> {
+    client: reqwest::Client,
+    memory: Mutex<HashMap<String, Arc<Icon>>>,
+    /// `None` when icons are only cached in `memory`.
+    storage: Option<S>,
+}
+impl IconLoader<MemoryStorage> {
+    /// Creates an icon loader that only caches icons in memory.
+    ///
+    /// Icons and their `ETag`s are lost when the loader is dropped, so every session downloads
+    /// them again. Use [`IconLoader::with_storage`] with e.g. [`crate::storage::LocalStorage`] to
+    /// keep them between sessions.
+    pub fn new(gamplo: &Gamplo) -> Self {
+        Self {
+            client: gamplo.http_client().clone(),
+            memory: Mutex::new(HashMap::new()),
+            storage: None,
+        }
+    }
+}
+impl<S: Storage> IconLoader<S> {
+    /// Creates an icon loader that also persists icons in `storage`.
+    pub fn with_storage(gamplo: &Gamplo, storage: S) -> Self {
+        Self {
+            client: gamplo.http_client().clone(),
+            memory: Mutex::new(HashMap::new()),
+            storage: Some(storage),
+        }
+    }
+    /// Loads the icon at `url`, using the cache when possible.
+    pub async fn load(&self, url: &str) -> Result<Arc<Icon>, GamploError> {
+        if let Some(icon) = self.cached(url) {
+            return Ok(icon);
+        }
+        self.refresh(url).await
+    }
+    /// Loads the icon of an achievement. Returns `None` if the achievement has no icon, as is the case for some hidden achievements.
+    pub async fn load_for(
+        &self,
+        achievement: &Achievement,
+    ) -> Result<Option<Arc<Icon>>, GamploError> {
+        self.load_optional(achievement.icon_url()).await
+    }
+    /// Loads the icon of an unlocked achievement. Returns `None` if the achievement has no icon.
+    pub async fn load_for_lite(
+        &self,
+        achievement: &AchievementLite,
+    ) -> Result<Option<Arc<Icon>>, GamploError> {
+        self.load_optional(achievement.icon_url()).await
+    }
+    /// Revalidates the icon at `url` against the server, bypassing the in-memory cache.
+    pub async fn refresh(&self, url: &str) -> Result<Arc<Icon>, GamploError> {
+        let stored = self.load_stored(url)?;
+        let mut request = self.client.get(url);
+        if let Some(etag) = stored.as_ref().and_then(|icon| icon.etag()) {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => {
+                return match stored {
+                    Some(icon) => Ok(self.remember(icon)),
+                    None => Err(err.into()),
+                };
+            }
+        };
+
+        if response.status() == StatusCode::NOT_MODIFIED || !response.status().is_success() {
+            if let Some(icon) = stored {
+                return Ok(self.remember(icon));
+            }
+            return Err(GamploError::ApiError(format!(
+                "Failed to fetch icon: {}, status: {}",
+                url,
+                response.status()
+            )));
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let content_type = header(CONTENT_TYPE).map(|t| {
+            t.split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+        });
+        let etag = header(ETAG);
+        let bytes = response.bytes().await?.to_vec();
+        let icon = Icon::new(url, bytes, content_type, etag);
+        self.store(&icon)?;
+        Ok(self.remember(icon))
+    }
+    /// Returns the icon at `url` if it is already cached in memory.
+    pub fn cached(&self, url: &str) -> Option<Arc<Icon>> {
+        self.memory.lock().unwrap().get(url).cloned()
+    }
+    /// Removes the icon at `url` from both the memory cache and storage.
+    pub fn evict(&self, url: &str) -> Result<(), GamploError> {
+        self.memory.lock().unwrap().remove(url);
+        match &self.storage {
+            Some(storage) => storage.remove(&storage_key(url)),
+            None => Ok(()),
+        }
+    }
+
+    async fn load_optional(&self, url: &str) -> Result<Option<Arc<Icon>>, GamploError> {
+        if url.is_empty() {
+            return Ok(None);
+        }
+        self.load(url).await.map(Some)
+    }
+    fn remember(&self, icon: Icon) -> Arc<Icon> {
+        let icon = Arc::new(icon);
+        self.memory
+            .lock()
+            .unwrap()
+            .insert(icon.url.clone(), icon.clone());
+        icon
+    }
+    fn load_stored(&self, url: &str) -> Result<Option<Icon>, GamploError> {
+        let Some(storage) = &self.storage else {
+            return Ok(None);
+        };
+        let Some(text) = storage.get(&storage_key(url))? else {
+            return Ok(None);
+        };
+        // A corrupted entry is treated as a cache miss rather than an error.
+        let Ok(stored) = serde_json::from_str::<StoredIcon>(&text) else {
+            return Ok(None);
+        };
+        let Ok(bytes) = BASE64.decode(stored.data) else {
+            return Ok(None);
+        };
+        Ok(Some(Icon::new(
+            url,
+            bytes,
+            Some(stored.content_type),
+            stored.etag,
+        )))
+    }
+    fn store(&self, icon: &Icon) -> Result<(), GamploError> {
+        let Some(storage) = &self.storage else {
+            return Ok(());
+        };
+        let stored = StoredIcon {
+            content_type: icon.content_type.clone(),
+            etag: icon.etag.clone(),
+            data: BASE64.encode(&icon.bytes),
+        };
+        storage.set(&storage_key(&icon.url), &serde_json::to_string(&stored)?)
+    }
+}
+
+fn storage_key(url: &str) -> String {
+    format!("{}{}", STORAGE_PREFIX, url)
+}
+
+/// Guesses the MIME type of an image from its magic bytes.
+fn sniff_content_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(256)]);
+        let head = head.trim_start();
+        (head.starts_with("<svg") || (head.starts_with("<?xml") && head.contains("<svg")))
+            .then_some("image/svg+xml")
+    }
+}
+
+/// Reads the pixel dimensions from the header of a PNG, GIF, JPEG or WebP image.
+fn sniff_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be32 = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let be16 = |at: usize| Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le16 = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le24 = |at: usize| {
+        let b = bytes.get(at..at + 3)?;
+        Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+    };
+
+    match sniff_content_type(bytes)? {
+        "image/png" => Some((be32(16)?, be32(20)?)),
+        "image/gif" => Some((le16(6)?, le16(8)?)),
+        "image/jpeg" => {
+            let mut at = 2;
+            while at + 9 < bytes.len() {
+                if bytes[at] != 0xff {
+                    return None;
+                }
+                let marker = bytes[at + 1];
+                // SOF0..SOF15, except DHT (C4), JPG (C8) and DAC (CC).
+                if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+                    return Some((be16(at + 7)?, be16(at + 5)?));
+                }
+                at += 2 + be16(at + 2)? as usize;
+            }
+            None
+        }
+        "image/webp" => match bytes.get(12..16)? {
+            b"VP8 " => Some((le16(26)? & 0x3fff, le16(28)? & 0x3fff)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&64u32.to_be_bytes());
+        png.extend_from_slice(&32u32.to_be_bytes());
+        assert_eq!(sniff_content_type(&png), Some("image/png"));
+        assert_eq!(sniff_dimensions(&png), Some((64, 32)));
+    }
+
+    #[test]
+    fn gif_dimensions() {
+        let gif = b"GIF89a\x80\x00\x40\x00";
+        assert_eq!(sniff_content_type(gif), Some("image/gif"));
+        assert_eq!(sniff_dimensions(gif), Some((128, 64)));
+    }
+
+    #[test]
+    fn jpeg_dimensions() {
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, // SOI + APP0
+            0xff, 0xc0, 0x00, 0x11, 0x08, 0x00, 0x30, 0x00, 0x40, 0x03, 0x00,
+            0x00, // SOF0 48x64
+        ];
+        assert_eq!(sniff_content_type(&jpeg), Some("image/jpeg"));
+        assert_eq!(sniff_dimensions(&jpeg), Some((64, 48)));
+    }
+
+    #[test]
+    fn stored_icon_round_trip() {
+        let storage = MemoryStorage::new();
+        let icon = Icon::new(
+            "https://example.com/icon.gif",
+            b"GIF89a\x10\x00\x10\x00".to_vec(),
+            None,
+            Some("\"abc\"".to_string()),
+        );
+        let loader = IconLoader {
+            client: reqwest::Client::new(),
+            memory: Mutex::new(HashMap::new()),
+            storage: Some(&storage),
+        };
+        loader.store(&icon).unwrap();
+        let loaded = loader.load_stored(icon.url()).unwrap().unwrap();
+        assert_eq!(loaded, icon);
+        assert_eq!(loaded.content_type(), "image/gif");
+        assert_eq!(loaded.dimensions(), Some((16, 16)));
+    }
+}
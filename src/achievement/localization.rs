@@ -0,0 +1,302 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use serde::Deserialize;
+
+use crate::{
+    achievement::{Achievement, AchievementLite},
+    error::GamploError,
+};
+
+/// Translated text for a single achievement. Missing fields fall back to the server text.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+pub struct AchievementText {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Achievement translations for one locale, keyed by achievement key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalizationBundle {
+    locale: String,
+    entries: HashMap<String, AchievementText>,
+}
+impl LocalizationBundle {
+    /// Creates an empty bundle for `locale` (e.g. `"fr"` or `"pt-BR"`).
+    pub fn new(locale: impl Into<String>) -> Self {
+        Self {
+            locale: locale.into(),
+            entries: HashMap::new(),
+        }
+    }
+    /// Parses a JSON bundle of the form:
+    ///
+    /// ```json
+    /// {
+    ///   "locale": "fr",
+    ///   "achievements": {
+    ///     "first_blood": { "title": "Premier sang", "description": "Battez votre premier ennemi" }
+    ///   }
+    /// }
+    /// ```
+    pub fn from_json(text: &str) -> Result<Self, GamploError> {
+        #[derive(Deserialize)]
+        struct JsonBundle {
+            locale: String,
+            achievements: HashMap<String, AchievementText>,
+        }
+        let parsed: JsonBundle =
+            serde_json::from_str(text).map_err(|e| GamploError::Deserialization {
+                type_name: "LocalizationBundle".to_string(),
+                data: text.to_string(),
+                source: e,
+            })?;
+        Ok(Self {
+            locale: parsed.locale,
+            entries: parsed.achievements,
+        })
+    }
+    /// Parses a Fluent-like bundle where each message is an achievement key, its value is the title
+    /// and its `.description` attribute is the description:
+    ///
+    /// ```text
+    /// # Comments and blank lines are ignored.
+    /// first_blood = Premier sang
+    ///     .description = Battez votre premier ennemi
+    /// ```
+    pub fn from_ftl(locale: impl Into<String>, text: &str) -> Result<Self, GamploError> {
+        let mut bundle = Self::new(locale);
+        let mut current: Option<String> = None;
+        for (number, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| {
+                GamploError::Localization(format!("line {}: {}: {}", number + 1, message, line))
+            };
+            let (name, value) = trimmed
+                .split_once('=')
+                .map(|(name, value)| (name.trim(), value.trim().to_string()))
+                .ok_or_else(|| error("expected `=`"))?;
+
+            if let Some(attribute) = name.strip_prefix('.') {
+                let key = current
+                    .as_ref()
+                    .filter(|_| line.starts_with(char::is_whitespace))
+                    .ok_or_else(|| error("attribute outside of a message"))?;
+                let entry = bundle.entries.entry(key.clone()).or_default();
+                match attribute {
+                    "title" => entry.title = Some(value),
+                    "description" => entry.description = Some(value),
+                    _ => return Err(error("unknown attribute")),
+                }
+            } else {
+                if name.is_empty() || line.starts_with(char::is_whitespace) {
+                    return Err(error("expected an achievement key"));
+                }
+                let entry = bundle.entries.entry(name.to_string()).or_default();
+                if !value.is_empty() {
+                    entry.title = Some(value);
+                }
+                current = Some(name.to_string());
+            }
+        }
+        Ok(bundle)
+    }
+    /// Adds or replaces the text for the achievement with `key`.
+    pub fn insert(&mut self, key: impl Into<String>, text: AchievementText) {
+        self.entries.insert(key.into(), text);
+    }
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+    pub fn get(&self, key: &str) -> Option<&AchievementText> {
+        self.entries.get(key)
+    }
+}
+
+/// Client-side overrides for achievement titles and descriptions, for games that ship in more
+/// languages than are configured on Gamplo.
+///
+/// Lookups try the active locale, then its parent locales (`pt-BR` falls back to `pt`),
+/// and finally keep the text returned by the server.
+/// Attach it to a client with [`crate::Gamplo::with_localization`] to localize the results of
+/// [`crate::Gamplo::get_achievements`] and [`crate::Gamplo::unlock_achievement`] automatically.
+#[derive(Debug, Default)]
+pub struct AchievementLocalization {
+    bundles: HashMap<String, LocalizationBundle>,
+    locale: RwLock<Option<String>>,
+}
+impl AchievementLocalization {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a bundle, merging it into any bundle already loaded for the same locale.
+    pub fn with_bundle(mut self, bundle: LocalizationBundle) -> Self {
+        self.add_bundle(bundle);
+        self
+    }
+    /// Adds a bundle, merging it into any bundle already loaded for the same locale.
+    pub fn add_bundle(&mut self, bundle: LocalizationBundle) {
+        let locale = normalize_locale(&bundle.locale);
+        match self.bundles.get_mut(&locale) {
+            Some(existing) => existing.entries.extend(bundle.entries),
+            None => {
+                self.bundles.insert(locale, bundle);
+            }
+        }
+    }
+    /// Sets the active locale. `None` disables overrides, keeping the server text.
+    pub fn set_locale(&self, locale: Option<&str>) {
+        *self.locale.write().unwrap() = locale.map(normalize_locale);
+    }
+    /// Sets the active locale.
+    pub fn with_locale(self, locale: &str) -> Self {
+        self.set_locale(Some(locale));
+        self
+    }
+    /// The active locale, normalized to lowercase with `-` separators.
+    pub fn locale(&self) -> Option<String> {
+        self.locale.read().unwrap().clone()
+    }
+    /// Returns the translated `(title, description)` for `key` in the active locale, if any.
+    pub fn lookup(&self, key: &str) -> (Option<&str>, Option<&str>) {
+        let (mut title, mut description) = (None, None);
+        let Some(locale) = self.locale() else {
+            return (title, description);
+        };
+        let mut candidate = locale.as_str();
+        loop {
+            if let Some(text) = self.bundles.get(candidate).and_then(|b| b.get(key)) {
+                title = title.or(text.title.as_deref());
+                description = description.or(text.description.as_deref());
+            }
+            if title.is_some() && description.is_some() {
+                break;
+            }
+            match candidate.rsplit_once('-') {
+                Some((parent, _)) => candidate = parent,
+                None => break,
+            }
+        }
+        (title, description)
+    }
+    /// Replaces the title and description of `achievement` with the active locale's text, if any.
+    ///
+    /// Hidden achievements that are still locked are left as the server masked them, so
+    /// translations don't reveal them.
+    pub fn localize(&self, achievement: &mut Achievement) {
+        if achievement.hidden && !achievement.unlocked {
+            return;
+        }
+        let (title, description) = self.lookup(&achievement.key);
+        override_text(&mut achievement.title, title);
+        override_text(&mut achievement.description, description);
+    }
+    /// Replaces the title and description of `achievement` with the active locale's text, if any.
+    ///
+    /// Lite achievements come from unlocking them, so they are never concealed.
+    pub fn localize_lite(&self, achievement: &mut AchievementLite) {
+        let (title, description) = self.lookup(&achievement.key);
+        override_text(&mut achievement.title, title);
+        override_text(&mut achievement.description, description);
+    }
+}
+
+fn override_text(target: &mut String, text: Option<&str>) {
+    if let Some(text) = text {
+        *target = text.to_string();
+    }
+}
+
+fn normalize_locale(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn achievement(key: &str) -> Achievement {
+        Achievement {
+            id: 0,
+            key: key.to_string(),
+            title: "Server title".to_string(),
+            description: "Server description".to_string(),
+            icon_url: String::new(),
+            points: 0,
+            hidden: false,
+            unlocked: false,
+            unlocked_at: None,
+            extra: Default::default(),
+        }
+    }
+
+    #[test]
+    fn json_bundle_with_fallback() {
+        let fr = LocalizationBundle::from_json(
+            r#"{"locale":"fr","achievements":{"first_blood":{"title":"Premier sang","description":"Battez votre premier ennemi"}}}"#,
+        )
+        .unwrap();
+        let fr_ca = LocalizationBundle::from_json(
+            r#"{"locale":"fr_CA","achievements":{"first_blood":{"title":"Premier sang!"}}}"#,
+        )
+        .unwrap();
+        let localization = AchievementLocalization::new()
+            .with_bundle(fr)
+            .with_bundle(fr_ca)
+            .with_locale("fr-CA");
+
+        let mut first_blood = achievement("first_blood");
+        localization.localize(&mut first_blood);
+        assert_eq!(first_blood.title(), "Premier sang!");
+        assert_eq!(first_blood.description(), "Battez votre premier ennemi");
+
+        let mut other = achievement("other");
+        localization.localize(&mut other);
+        assert_eq!(other.title(), "Server title");
+
+        // Hidden achievements stay masked until they are unlocked.
+        let mut secret = Achievement {
+            title: "???".to_string(),
+            description: String::new(),
+            hidden: true,
+            ..achievement("first_blood")
+        };
+        localization.localize(&mut secret);
+        assert_eq!(secret.title(), "???");
+        assert_eq!(secret.description(), "");
+        secret.unlocked = true;
+        localization.localize(&mut secret);
+        assert_eq!(secret.title(), "Premier sang!");
+
+        localization.set_locale(Some("de"));
+        let mut first_blood = achievement("first_blood");
+        localization.localize(&mut first_blood);
+        assert_eq!(first_blood.title(), "Server title");
+    }
+
+    #[test]
+    fn ftl_bundle() {
+        let bundle = LocalizationBundle::from_ftl(
+            "de",
+            "# Erfolge\nfirst_blood = Erstes Blut\n    .description = Besiege deinen ersten Gegner\n\nspeedrunner =\n    .title = Speedrunner\n",
+        )
+        .unwrap();
+        let first_blood = bundle.get("first_blood").unwrap();
+        assert_eq!(first_blood.title.as_deref(), Some("Erstes Blut"));
+        assert_eq!(
+            first_blood.description.as_deref(),
+            Some("Besiege deinen ersten Gegner")
+        );
+        assert_eq!(
+            bundle.get("speedrunner").unwrap().title.as_deref(),
+            Some("Speedrunner")
+        );
+
+        assert!(LocalizationBundle::from_ftl("de", ".description = oops").is_err());
+        assert!(LocalizationBundle::from_ftl("de", "no equals sign").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{achievement::Achievement, backend::GamploBackend, error::GamploError};
+
+/// The shortest interval [`AchievementPoller`] will suggest between polls by default.
+pub const DEFAULT_MIN_POLL_INTERVAL: Duration = Duration::from_secs(15);
+/// The longest interval [`AchievementPoller`] will back off to by default.
+pub const DEFAULT_MAX_POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// The shortest interval [`AchievementPoller`] accepts, so a zero minimum can't poll in a busy loop.
+pub const MIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A change detected between two achievement snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AchievementEvent {
+    /// The achievement was unlocked since the last snapshot, e.g. on another device or by the server.
+    Unlocked(Achievement),
+    /// The achievement was unlocked in the last snapshot but is now locked again.
+    Locked(Achievement),
+    /// The achievement stayed unlocked but its unlock time changed.
+    UnlockTimeChanged {
+        previous: Achievement,
+        current: Achievement,
+    },
+    /// An achievement that was not part of the last snapshot.
+    Added(Achievement),
+    /// An achievement that was part of the last snapshot but is no longer returned.
+    Removed(Achievement),
+}
+impl AchievementEvent {
+    /// The key of the achievement this event is about.
+    pub fn key(&self) -> &str {
+        match self {
+            AchievementEvent::Unlocked(a)
+            | AchievementEvent::Locked(a)
+            | AchievementEvent::Added(a)
+            | AchievementEvent::Removed(a) => a.key(),
+            AchievementEvent::UnlockTimeChanged { current, .. } => current.key(),
+        }
+    }
+}
+
+/// Periodically diffs [`GamploBackend::get_achievements`] against the last snapshot to detect
+/// achievements unlocked outside of the running client.
+///
+/// The poller does not own a timer. Call [`AchievementPoller::poll`] and wait
+/// [`AchievementPoller::next_interval`] before polling again. The interval shrinks back to the
+/// minimum whenever something changes and doubles (up to the maximum) while nothing does.
+/// Use [`AchievementPoller::pause`] and [`AchievementPoller::resume`] when the game is hidden.
+#[derive(Debug, Clone)]
+pub struct AchievementPoller {
+    snapshot: Option<HashMap<String, Achievement>>,
+    min_interval: Duration,
+    max_interval: Duration,
+    interval: Duration,
+    paused: bool,
+}
+impl Default for AchievementPoller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl AchievementPoller {
+    /// Creates a poller using [`DEFAULT_MIN_POLL_INTERVAL`] and [`DEFAULT_MAX_POLL_INTERVAL`].
+    pub fn new() -> Self {
+        Self::with_intervals(DEFAULT_MIN_POLL_INTERVAL, DEFAULT_MAX_POLL_INTERVAL)
+    }
+    /// Creates a poller that adapts its interval between `min` and `max`.
+    ///
+    /// `min` is raised to at least [`MIN_POLL_INTERVAL`].
+    pub fn with_intervals(min: Duration, max: Duration) -> Self {
+        let min = min.max(MIN_POLL_INTERVAL);
+        let max = max.max(min);
+        Self {
+            snapshot: None,
+            min_interval: min,
+            max_interval: max,
+            interval: min,
+            paused: false,
+        }
+    }
+    /// Seeds the poller with achievements the game already fetched, so the first poll reports changes against them.
+    pub fn with_snapshot(mut self, achievements: Vec<Achievement>) -> Self {
+        self.snapshot = Some(Self::index(achievements));
+        self
+    }
+    /// Fetches the achievements and returns the changes since the last snapshot.
+    ///
+    /// The first poll without a seeded snapshot only records the baseline and returns no events.
+    /// While paused, no request is made and no events are returned.
+    pub async fn poll(
+        &mut self,
+        backend: &impl GamploBackend,
+    ) -> Result<Vec<AchievementEvent>, GamploError> {
+        if self.paused {
+            return Ok(Vec::new());
+        }
+        match backend.get_achievements().await {
+            Ok(achievements) => Ok(self.update(achievements)),
+            Err(err) => {
+                self.back_off();
+                Err(err)
+            }
+        }
+    }
+    /// Replaces the snapshot with `achievements` and returns the changes since the previous one.
+    ///
+    /// Useful when achievements are fetched by other means, e.g. after [`GamploBackend::unlock_achievement`].
+    pub fn update(&mut self, achievements: Vec<Achievement>) -> Vec<AchievementEvent> {
+        let current = Self::index(achievements.iter().cloned());
+        let events = match self.snapshot.take() {
+            Some(mut previous) => {
+                let mut events = Vec::new();
+                for achievement in achievements {
+                    match previous.remove(achievement.key()) {
+                        None => events.push(AchievementEvent::Added(achievement)),
+                        Some(old) => {
+                            if let Some(event) = Self::compare(old, achievement) {
+                                events.push(event);
+                            }
+                        }
+                    }
+                }
+                let mut removed: Vec<_> = previous.into_values().collect();
+                removed.sort_by(|a, b| a.key.cmp(&b.key));
+                events.extend(removed.into_iter().map(AchievementEvent::Removed));
+                events
+            }
+            None => Vec::new(),
+        };
+        self.snapshot = Some(current);
+        if events.is_empty() {
+            self.back_off();
+        } else {
+            self.interval = self.min_interval;
+        }
+        events
+    }
+    /// How long to wait before the next call to [`AchievementPoller::poll`].
+    pub fn next_interval(&self) -> Duration {
+        self.interval
+    }
+    /// Stops polling, e.g. when the tab becomes hidden.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    /// Resumes polling and resets the interval to the minimum so changes are picked up quickly.
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.interval = self.min_interval;
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// The achievements from the last successful poll, if any.
+    pub fn snapshot(&self) -> impl Iterator<Item = &Achievement> {
+        self.snapshot.iter().flat_map(|s| s.values())
+    }
+
+    fn back_off(&mut self) {
+        self.interval = self.interval.saturating_mul(2).min(self.max_interval);
+    }
+    fn index(achievements: impl IntoIterator<Item = Achievement>) -> HashMap<String, Achievement> {
+        achievements
+            .into_iter()
+            .map(|a| (a.key.clone(), a))
+            .collect()
+    }
+    fn compare(previous: Achievement, current: Achievement) -> Option<AchievementEvent> {
+        match (previous.unlocked, current.unlocked) {
+            (false, true) => Some(AchievementEvent::Unlocked(current)),
+            (true, false) => Some(AchievementEvent::Locked(current)),
+            (true, true) if previous.unlocked_at != current.unlocked_at => {
+                Some(AchievementEvent::UnlockTimeChanged { previous, current })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn achievement(key: &str, unlocked: bool) -> Achievement {
+        Achievement {
+            id: 1,
+            key: key.to_string(),
+            title: key.to_string(),
+            description: String::new(),
+            icon_url: String::new(),
+            points: 10,
+            hidden: false,
+            unlocked,
+            unlocked_at: unlocked.then_some(chrono::DateTime::UNIX_EPOCH),
+            extra: Default::default(),
+        }
+    }
+
+    #[test]
+    fn first_update_is_baseline() {
+        let mut poller = AchievementPoller::new();
+        assert!(poller.update(vec![achievement("a", true)]).is_empty());
+        assert_eq!(poller.snapshot().count(), 1);
+    }
+
+    #[test]
+    fn detects_changes() {
+        let mut poller = AchievementPoller::new().with_snapshot(vec![
+            achievement("a", false),
+            achievement("b", true),
+            achievement("c", false),
+        ]);
+        let events = poller.update(vec![
+            achievement("a", true),
+            achievement("b", false),
+            achievement("d", false),
+        ]);
+        assert_eq!(
+            events,
+            vec![
+                AchievementEvent::Unlocked(achievement("a", true)),
+                AchievementEvent::Locked(achievement("b", false)),
+                AchievementEvent::Added(achievement("d", false)),
+                AchievementEvent::Removed(achievement("c", false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn adaptive_interval() {
+        let min = Duration::from_secs(1);
+        let max = Duration::from_secs(3);
+        let mut poller = AchievementPoller::with_intervals(min, max);
+        poller.update(vec![achievement("a", false)]);
+        assert_eq!(poller.next_interval(), Duration::from_secs(2));
+        poller.update(vec![achievement("a", false)]);
+        assert_eq!(poller.next_interval(), max);
+        poller.update(vec![achievement("a", true)]);
+        assert_eq!(poller.next_interval(), min);
+
+        poller.update(vec![achievement("a", true)]);
+        poller.pause();
+        assert!(poller.is_paused());
+        poller.resume();
+        assert_eq!(poller.next_interval(), min);
+
+        let mut unbounded = AchievementPoller::with_intervals(Duration::MAX / 2, Duration::MAX);
+        for _ in 0..3 {
+            unbounded.update(vec![achievement("a", true)]);
+        }
+        assert_eq!(unbounded.next_interval(), Duration::MAX);
+
+        let mut zero = AchievementPoller::with_intervals(Duration::ZERO, Duration::ZERO);
+        assert_eq!(zero.next_interval(), MIN_POLL_INTERVAL);
+        zero.update(vec![achievement("a", true)]);
+        assert_eq!(zero.next_interval(), MIN_POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,356 @@
+use std::cmp::Reverse;
+
+use serde::Serialize;
+
+use crate::achievement::Achievement;
+
+/// How achievements are ordered on an achievement screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AchievementSort {
+    /// The order returned by [`crate::Gamplo::get_achievements`].
+    #[default]
+    Default,
+    /// Most recently unlocked first, followed by locked achievements in the default order.
+    RecentlyUnlocked,
+    /// Earliest unlocked first, followed by locked achievements in the default order.
+    FirstUnlocked,
+    /// Most points first.
+    PointsDescending,
+    /// Fewest points first.
+    PointsAscending,
+    /// Alphabetically by displayed title. Concealed achievements sort by their placeholder title.
+    Title,
+}
+
+/// Which achievements are shown on an achievement screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AchievementFilter {
+    #[default]
+    All,
+    Unlocked,
+    Locked,
+    /// Everything except hidden achievements that are still locked.
+    Revealed,
+}
+
+/// How achievements are grouped on an achievement screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AchievementGrouping {
+    /// A single [`AchievementGroupKind::All`] group.
+    #[default]
+    None,
+    /// An [`AchievementGroupKind::Unlocked`] group followed by an [`AchievementGroupKind::Locked`] group.
+    ByStatus,
+}
+
+/// Options for building an [`AchievementScreen`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AchievementViewOptions {
+    pub sort: AchievementSort,
+    pub filter: AchievementFilter,
+    pub grouping: AchievementGrouping,
+    /// Title shown instead of the real one for hidden achievements that are still locked.
+    pub hidden_title: String,
+    /// Description shown instead of the real one for hidden achievements that are still locked.
+    pub hidden_description: String,
+    /// How many achievements to include in [`AchievementScreen::recently_unlocked`].
+    pub recent_count: usize,
+}
+impl Default for AchievementViewOptions {
+    fn default() -> Self {
+        Self {
+            sort: AchievementSort::default(),
+            filter: AchievementFilter::default(),
+            grouping: AchievementGrouping::default(),
+            hidden_title: "Hidden achievement".to_string(),
+            hidden_description: "Keep playing to reveal this achievement.".to_string(),
+            recent_count: 3,
+        }
+    }
+}
+
+/// An achievement ready to be displayed, with spoilers of hidden locked achievements removed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AchievementView {
+    pub key: String,
+    pub title: String,
+    pub description: String,
+    /// `None` if the achievement has no icon or is concealed.
+    pub icon_url: Option<String>,
+    pub points: u32,
+    pub hidden: bool,
+    pub unlocked: bool,
+    pub unlocked_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Whether the title, description and icon were replaced because the achievement is hidden and still locked.
+    pub concealed: bool,
+}
+impl AchievementView {
+    /// Creates a view of `achievement`, concealing it with the placeholders from `options` if it is hidden and locked.
+    pub fn new(achievement: &Achievement, options: &AchievementViewOptions) -> Self {
+        let concealed = achievement.hidden && !achievement.unlocked;
+        let (title, description, icon_url) = if concealed {
+            (
+                options.hidden_title.clone(),
+                options.hidden_description.clone(),
+                None,
+            )
+        } else {
+            (
+                achievement.title.clone(),
+                achievement.description.clone(),
+                Some(achievement.icon_url.clone()).filter(|url| !url.is_empty()),
+            )
+        };
+        Self {
+            key: achievement.key.clone(),
+            title,
+            description,
+            icon_url,
+            points: achievement.points,
+            hidden: achievement.hidden,
+            unlocked: achievement.unlocked,
+            unlocked_at: achievement.unlocked_at,
+            concealed,
+        }
+    }
+}
+
+/// Which achievements an [`AchievementGroup`] contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AchievementGroupKind {
+    All,
+    Unlocked,
+    Locked,
+}
+
+/// A titled section of an achievement screen.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AchievementGroup {
+    pub kind: AchievementGroupKind,
+    pub achievements: Vec<AchievementView>,
+}
+
+/// Completion statistics over every achievement, regardless of filtering.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionStats {
+    pub unlocked: u32,
+    pub total: u32,
+    /// Summed as `u64`, so many high-value achievements can't overflow it.
+    pub points_earned: u64,
+    pub points_total: u64,
+}
+impl CompletionStats {
+    pub fn new(achievements: &[Achievement]) -> Self {
+        achievements
+            .iter()
+            .fold(Self::default(), |mut stats, achievement| {
+                stats.total += 1;
+                stats.points_total += u64::from(achievement.points);
+                if achievement.unlocked {
+                    stats.unlocked += 1;
+                    stats.points_earned += u64::from(achievement.points);
+                }
+                stats
+            })
+    }
+    /// Percentage (0-100) of achievements unlocked. `0.0` if there are no achievements.
+    pub fn percent(&self) -> f32 {
+        percent(self.unlocked.into(), self.total.into())
+    }
+    /// Percentage (0-100) of points earned. `0.0` if there are no points to earn.
+    pub fn points_percent(&self) -> f32 {
+        percent(self.points_earned, self.points_total)
+    }
+    pub fn is_complete(&self) -> bool {
+        self.total > 0 && self.unlocked == self.total
+    }
+}
+
+/// Everything needed to render an in-game achievement screen.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AchievementScreen {
+    pub groups: Vec<AchievementGroup>,
+    pub stats: CompletionStats,
+    /// The most recently unlocked achievements, newest first.
+    pub recently_unlocked: Vec<AchievementView>,
+}
+impl AchievementScreen {
+    /// Builds the screen from the achievements returned by [`crate::Gamplo::get_achievements`].
+    pub fn new(achievements: &[Achievement], options: &AchievementViewOptions) -> Self {
+        let mut views: Vec<AchievementView> = achievements
+            .iter()
+            .filter(|a| match options.filter {
+                AchievementFilter::All => true,
+                AchievementFilter::Unlocked => a.unlocked,
+                AchievementFilter::Locked => !a.unlocked,
+                AchievementFilter::Revealed => a.unlocked || !a.hidden,
+            })
+            .map(|a| AchievementView::new(a, options))
+            .collect();
+        sort(&mut views, options.sort);
+
+        let groups = match options.grouping {
+            AchievementGrouping::None => vec![AchievementGroup {
+                kind: AchievementGroupKind::All,
+                achievements: views,
+            }],
+            AchievementGrouping::ByStatus => {
+                let (unlocked, locked) = views.into_iter().partition(|v| v.unlocked);
+                vec![
+                    AchievementGroup {
+                        kind: AchievementGroupKind::Unlocked,
+                        achievements: unlocked,
+                    },
+                    AchievementGroup {
+                        kind: AchievementGroupKind::Locked,
+                        achievements: locked,
+                    },
+                ]
+            }
+        };
+
+        let mut recently_unlocked: Vec<AchievementView> = achievements
+            .iter()
+            .filter(|a| a.unlocked)
+            .map(|a| AchievementView::new(a, options))
+            .collect();
+        sort(&mut recently_unlocked, AchievementSort::RecentlyUnlocked);
+        recently_unlocked.truncate(options.recent_count);
+
+        Self {
+            groups,
+            stats: CompletionStats::new(achievements),
+            recently_unlocked,
+        }
+    }
+    /// Iterates over every displayed achievement across all groups.
+    pub fn achievements(&self) -> impl Iterator<Item = &AchievementView> {
+        self.groups.iter().flat_map(|g| g.achievements.iter())
+    }
+}
+
+fn sort(views: &mut [AchievementView], sort: AchievementSort) {
+    // All sorts are stable, so ties keep the default order.
+    match sort {
+        AchievementSort::Default => {}
+        AchievementSort::RecentlyUnlocked => {
+            views.sort_by_key(|v| (!v.unlocked, Reverse(v.unlocked_at)))
+        }
+        AchievementSort::FirstUnlocked => views.sort_by_key(|v| {
+            (
+                !v.unlocked,
+                v.unlocked_at
+                    .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC),
+            )
+        }),
+        AchievementSort::PointsDescending => views.sort_by_key(|v| Reverse(v.points)),
+        AchievementSort::PointsAscending => views.sort_by_key(|v| v.points),
+        AchievementSort::Title => views.sort_by_cached_key(|v| v.title.to_lowercase()),
+    }
+}
+
+fn percent(part: u64, total: u64) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f32 / total as f32 * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn achievement(key: &str, points: u32, hidden: bool, unlocked_at: Option<i64>) -> Achievement {
+        Achievement {
+            id: 0,
+            key: key.to_string(),
+            title: key.to_uppercase(),
+            description: format!("{} description", key),
+            icon_url: format!("https://example.com/{}.png", key),
+            points,
+            hidden,
+            unlocked: unlocked_at.is_some(),
+            unlocked_at: unlocked_at.and_then(|t| chrono::DateTime::from_timestamp(t, 0)),
+            extra: Default::default(),
+        }
+    }
+
+    fn sample() -> Vec<Achievement> {
+        vec![
+            achievement("a", 10, false, Some(100)),
+            achievement("b", 50, false, None),
+            achievement("c", 25, true, None),
+            achievement("d", 15, true, Some(200)),
+        ]
+    }
+
+    #[test]
+    fn conceals_hidden_locked() {
+        let options = AchievementViewOptions::default();
+        let screen = AchievementScreen::new(&sample(), &options);
+        let views: Vec<_> = screen.achievements().collect();
+        assert_eq!(views.len(), 4);
+        assert!(views[2].concealed);
+        assert_eq!(views[2].title, options.hidden_title);
+        assert_eq!(views[2].icon_url, None);
+        assert!(!views[3].concealed);
+        assert_eq!(views[3].title, "D");
+    }
+
+    #[test]
+    fn sorts_groups_and_filters() {
+        let options = AchievementViewOptions {
+            sort: AchievementSort::RecentlyUnlocked,
+            grouping: AchievementGrouping::ByStatus,
+            filter: AchievementFilter::Revealed,
+            ..Default::default()
+        };
+        let screen = AchievementScreen::new(&sample(), &options);
+        fn keys(group: &AchievementGroup) -> Vec<&str> {
+            group.achievements.iter().map(|v| v.key.as_str()).collect()
+        }
+        assert_eq!(keys(&screen.groups[0]), ["d", "a"]);
+        assert_eq!(keys(&screen.groups[1]), ["b"]);
+
+        let options = AchievementViewOptions {
+            sort: AchievementSort::PointsDescending,
+            ..Default::default()
+        };
+        let screen = AchievementScreen::new(&sample(), &options);
+        assert_eq!(keys(&screen.groups[0]), ["b", "c", "d", "a"]);
+    }
+
+    #[test]
+    fn completion_stats() {
+        let options = AchievementViewOptions {
+            recent_count: 1,
+            filter: AchievementFilter::Locked,
+            ..Default::default()
+        };
+        let screen = AchievementScreen::new(&sample(), &options);
+        assert_eq!(screen.stats.unlocked, 2);
+        assert_eq!(screen.stats.total, 4);
+        assert_eq!(screen.stats.points_earned, 25);
+        assert_eq!(screen.stats.points_total, 100);
+        assert_eq!(screen.stats.percent(), 50.0);
+        assert_eq!(screen.stats.points_percent(), 25.0);
+        assert!(!screen.stats.is_complete());
+
+        let big = [
+            achievement("x", u32::MAX, false, Some(1)),
+            achievement("y", u32::MAX, false, None),
+        ];
+        let stats = CompletionStats::new(&big);
+        assert_eq!(stats.points_total, 2 * u64::from(u32::MAX));
+        assert_eq!(stats.points_percent(), 50.0);
+        assert_eq!(screen.recently_unlocked.len(), 1);
+        assert_eq!(screen.recently_unlocked[0].key, "d");
+        assert_eq!(CompletionStats::new(&[]).percent(), 0.0);
+    }
+}
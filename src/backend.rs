@@ -0,0 +1,158 @@
+use serde_json::Value;
+
+use crate::{
+    Gamplo, ModerationResult,
+    achievement::{Achievement, AchievementUnlockResponse},
+    error::GamploError,
+    moderation::{self, report::ModerationReport},
+    player::Player,
+    save::{
+        self, AllSaves, SaveData, SaveDeleteResponse, SaveWriteResponse, Saves, header::SaveHeader,
+    },
+};
+
+/// The Gamplo API, implemented by [`Gamplo`] and by [`crate::local::LocalBackend`] so games can
+/// keep working without a Gamplo account (e.g. on itch.io or while developing locally).
+///
+/// Code written against this trait runs unchanged against either backend.
+// The HTTP client's futures are not `Send` on wasm, so no auto trait bounds are promised.
+#[allow(async_fn_in_trait)]
+pub trait GamploBackend {
+    /// See [`Gamplo::get_player`].
+    async fn get_player(&self) -> Result<Option<Player>, GamploError>;
+    /// See [`Gamplo::get_achievements`].
+    async fn get_achievements(&self) -> Result<Vec<Achievement>, GamploError>;
+    /// See [`Gamplo::unlock_achievement`].
+    async fn unlock_achievement(
+        &self,
+        achievement: &str,
+    ) -> Result<AchievementUnlockResponse, GamploError>;
+    /// See [`Gamplo::get_saves`].
+    async fn get_saves(&self) -> Result<Saves, GamploError>;
+    /// See [`Gamplo::get_save`].
+    async fn get_save(&self, slot: u32) -> Result<Option<SaveData>, GamploError>;
+    /// See [`Gamplo::save`]. Removes the slot's header.
+    async fn save(&self, slot: Option<u32>, data: Value) -> Result<SaveWriteResponse, GamploError>;
+    /// See [`Gamplo::save_with_header`]. Replaces the slot's header, removing it if `header` is `None`.
+    async fn save_with_header(
+        &self,
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        data: Value,
+    ) -> Result<SaveWriteResponse, GamploError>;
+    /// See [`Gamplo::delete_save`].
+    async fn delete_save(&self, slot: u32) -> Result<SaveDeleteResponse, GamploError>;
+    /// See [`Gamplo::moderate`].
+    async fn moderate(&self, text: &str) -> Result<ModerationResult, GamploError>;
+
+    /// See [`Gamplo::get_all_saves`].
+    async fn get_all_saves(&self, concurrency: usize) -> Result<AllSaves, GamploError> {
+        save::get_all_saves(self, concurrency).await
+    }
+    /// See [`Gamplo::moderate_detailed`].
+    ///
+    /// Backends without detailed results report only the result of [`GamploBackend::moderate`].
+    async fn moderate_detailed(&self, text: &str) -> Result<ModerationReport, GamploError> {
+        Ok(self.moderate(text).await?.into())
+    }
+    /// See [`Gamplo::moderate_many`].
+    async fn moderate_many(
+        &self,
+        texts: &[&str],
+        concurrency: usize,
+    ) -> Result<Vec<ModerationResult>, GamploError> {
+        moderation::moderate_many(self, texts, concurrency).await
+    }
+}
+
+impl GamploBackend for Gamplo {
+    async fn get_player(&self) -> Result<Option<Player>, GamploError> {
+        Gamplo::get_player(self).await
+    }
+    async fn get_achievements(&self) -> Result<Vec<Achievement>, GamploError> {
+        Gamplo::get_achievements(self).await
+    }
+    async fn unlock_achievement(
+        &self,
+        achievement: &str,
+    ) -> Result<AchievementUnlockResponse, GamploError> {
+        Gamplo::unlock_achievement(self, achievement).await
+    }
+    async fn get_saves(&self) -> Result<Saves, GamploError> {
+        Gamplo::get_saves(self).await
+    }
+    async fn get_save(&self, slot: u32) -> Result<Option<SaveData>, GamploError> {
+        Gamplo::get_save(self, slot).await
+    }
+    async fn save(&self, slot: Option<u32>, data: Value) -> Result<SaveWriteResponse, GamploError> {
+        Gamplo::save(self, slot, data).await
+    }
+    async fn save_with_header(
+        &self,
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        data: Value,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        Gamplo::save_with_header(self, slot, header, data).await
+    }
+    async fn delete_save(&self, slot: u32) -> Result<SaveDeleteResponse, GamploError> {
+        Gamplo::delete_save(self, slot).await
+    }
+    async fn moderate(&self, text: &str) -> Result<ModerationResult, GamploError> {
+        Gamplo::moderate(self, text).await
+    }
+    async fn moderate_detailed(&self, text: &str) -> Result<ModerationReport, GamploError> {
+        Gamplo::moderate_detailed(self, text).await
+    }
+}
+
+impl<B: GamploBackend + ?Sized> GamploBackend for &B {
+    async fn get_player(&self) -> Result<Option<Player>, GamploError> {
+        (**self).get_player().await
+    }
+    async fn get_achievements(&self) -> Result<Vec<Achievement>, GamploError> {
+        (**self).get_achievements().await
+    }
+    async fn unlock_achievement(
+        &self,
+        achievement: &str,
+    ) -> Result<AchievementUnlockResponse, GamploError> {
+        (**self).unlock_achievement(achievement).await
+    }
+    async fn get_saves(&self) -> Result<Saves, GamploError> {
+        (**self).get_saves().await
+    }
+    async fn get_save(&self, slot: u32) -> Result<Option<SaveData>, GamploError> {
+        (**self).get_save(slot).await
+    }
+    async fn save(&self, slot: Option<u32>, data: Value) -> Result<SaveWriteResponse, GamploError> {
+        (**self).save(slot, data).await
+    }
+    async fn save_with_header(
+        &self,
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        data: Value,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        (**self).save_with_header(slot, header, data).await
+    }
+    async fn delete_save(&self, slot: u32) -> Result<SaveDeleteResponse, GamploError> {
+        (**self).delete_save(slot).await
+    }
+    async fn moderate(&self, text: &str) -> Result<ModerationResult, GamploError> {
+        (**self).moderate(text).await
+    }
+    async fn moderate_detailed(&self, text: &str) -> Result<ModerationReport, GamploError> {
+        (**self).moderate_detailed(text).await
+    }
+    async fn get_all_saves(&self, concurrency: usize) -> Result<AllSaves, GamploError> {
+        (**self).get_all_saves(concurrency).await
+    }
+    async fn moderate_many(
+        &self,
+        texts: &[&str],
+        concurrency: usize,
+    ) -> Result<Vec<ModerationResult>, GamploError> {
+        (**self).moderate_many(texts, concurrency).await
+    }
+}
@@ -27,7 +27,78 @@ pub enum GamploError {
     #[error("Token not found in query parameters")]
     TokenNotFound(String),
 
+    #[error("Invalid save slot: {slot}, slots range from 1 to {max_slots}")]
+    InvalidSlot { slot: u32, max_slots: u32 },
+
+    #[error("Save slot {slot} is empty")]
+    EmptySlot { slot: u32 },
+
+    #[error("Invalid save slot configuration: {0}")]
+    SlotConfig(String),
+
+    #[error("Invalid save bundle: {0}")]
+    InvalidBundle(String),
+
+    #[error("Failed to apply patch {0}")]
+    PatchApply(String),
+
+    #[error("Save slot {slot} is corrupted: {reason}")]
+    SaveCorrupted { slot: u32, reason: String },
+
+    #[error("Save slot {slot} failed its integrity check and may have been tampered with")]
+    SaveTampered { slot: u32 },
+
+    #[error("Save slot {slot} could not be decrypted: {reason}")]
+    SaveDecryption { slot: u32, reason: String },
+
+    #[error(
+        "Save data does not match its schema: {}",
+        .violations.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    SaveValidation {
+        /// The slot the data was read from or written to, if known.
+        slot: Option<u32>,
+        violations: Vec<crate::save::schema::SchemaViolation>,
+    },
+
+    #[error("Invalid save schema: {0}")]
+    InvalidSchema(String),
+
+    #[error(
+        "Save data contains text blocked by moderation at {}",
+        .blocked.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    SaveModeration {
+        blocked: Vec<crate::save::moderation::BlockedText>,
+    },
+
+    #[error("Failed to encode save data: {0}")]
+    SaveEncoding(String),
+
+    #[error(
+        "Keepalive save is {size} bytes, more than the {remaining} bytes left of the browser's keepalive limit"
+    )]
+    KeepaliveTooLarge { size: usize, remaining: usize },
+
+    #[error("Encryption failed: {0}")]
+    Encryption(String),
+
+    #[error("Invalid localization bundle: {0}")]
+    Localization(String),
+
+    #[error(
+        "Text was blocked by moderation{}",
+        .reason.as_ref().map(|reason| format!(": {}", reason)).unwrap_or_default()
+    )]
+    ModerationBlocked { reason: Option<String> },
+
+    #[error("Invalid moderation rule {0}")]
+    ModerationRule(String),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
     #[cfg(target_arch = "wasm32")]
     #[error("WASM error: {0}")]
     Wasm(String),
-}
\ No newline at end of file
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeSet;
+
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+
+use crate::{backend::GamploBackend, error::GamploError};
+
+/// A `localStorage`-like key-value store synced to the player's save slots.
+///
+/// Keys are spread over the configured slots by hash, and each slot holds a JSON object of the keys mapped to it.
+/// Values are cached in memory after [`GamploKv::load`]; [`GamploKv::set`] and
+/// [`GamploKv::remove`] only change the cache, and [`GamploKv::flush`] writes every changed slot at once.
+#[derive(Debug)]
+pub struct GamploKv<B> {
+    backend: B,
+    slots: Vec<(u32, Map<String, Value>)>,
+    dirty: BTreeSet<usize>,
+}
+impl<B: GamploBackend> GamploKv<B> {
+    /// Loads the key-value store kept in `slots`. Empty slots start out as empty objects.
+    ///
+    /// Fails with [`GamploError::SlotConfig`] if `slots` is empty or lists a slot more than once.
+    pub async fn load(
+        backend: B,
+        slots: impl IntoIterator<Item = u32>,
+    ) -> Result<Self, GamploError> {
+        let mut loaded = Vec::new();
+        for slot in slots {
+            if loaded.iter().any(|(loaded, _)| *loaded == slot) {
+                return Err(GamploError::SlotConfig(format!(
+                    "slot {} is used more than once in a key-value store",
+                    slot
+                )));
+            }
+            let values = match backend.get_save(slot).await? {
+                None => Map::new(),
+                Some(save) => match save.data {
+                    Value::Object(values) => values,
+                    Value::Null => Map::new(),
+                    other => {
+                        return Err(GamploError::SaveCorrupted {
+                            slot,
+                            reason: format!("expected a key-value object, found {}", other),
+                        });
+                    }
+                },
+            };
+            loaded.push((slot, values));
+        }
+        if loaded.is_empty() {
+            return Err(GamploError::SlotConfig(
+                "a key-value store needs at least one save slot".to_string(),
+            ));
+        }
+        Ok(Self {
+            backend,
+            slots: loaded,
+            dirty: BTreeSet::new(),
+        })
+    }
+    /// Gets the raw JSON value stored under `key`.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.slots.iter().find_map(|(_, values)| values.get(key))
+    }
+    /// Gets the value stored under `key`, deserialized as `T`.
+    pub fn get_as<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, GamploError> {
+        self.get(key)
+            .map(|value| {
+                serde_json::from_value(value.clone()).map_err(|e| GamploError::Deserialization {
+                    type_name: std::any::type_name::<T>().to_string(),
+                    data: value.to_string(),
+                    source: e,
+                })
+            })
+            .transpose()
+    }
+    /// Stores `value` under `key`. The change is written on the next [`GamploKv::flush`].
+    pub fn set<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), GamploError> {
+        let value = serde_json::to_value(value)?;
+        let index = self.index_of(key).unwrap_or_else(|| self.home(key));
+        if self.slots[index].1.get(key) != Some(&value) {
+            self.slots[index].1.insert(key.to_string(), value);
+            self.dirty.insert(index);
+        }
+        Ok(())
+    }
+    /// Removes `key`, returning its previous value. The change is written on the next [`GamploKv::flush`].
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let index = self.index_of(key)?;
+        self.dirty.insert(index);
+        self.slots[index].1.remove(key)
+    }
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index_of(key).is_some()
+    }
+    /// Iterates over every stored key.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.slots
+            .iter()
+            .flat_map(|(_, values)| values.keys().map(String::as_str))
+    }
+    /// Whether there are changes that have not been flushed yet.
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+    /// Writes every slot with unflushed changes.
+    ///
+    /// If a write fails, that slot and the ones after it stay dirty so the next flush retries them.
+    ///
+    /// Slots are written without a header, so any header they had is removed.
+    pub async fn flush(&mut self) -> Result<(), GamploError> {
+        while let Some(&index) = self.dirty.first() {
+            let (slot, values) = &self.slots[index];
+            self.backend
+                .save(Some(*slot), Value::Object(values.clone()))
+                .await?;
+            self.dirty.remove(&index);
+        }
+        Ok(())
+    }
+    /// Returns the backend this store syncs to.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    fn index_of(&self, key: &str) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|(_, values)| values.contains_key(key))
+    }
+    /// The slot a new key is stored in. Uses FNV-1a so the mapping is stable across builds.
+    fn home(&self, key: &str) -> usize {
+        let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        (hash % self.slots.len() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{local::LocalBackend, util::block_on};
+
+    #[test]
+    fn set_flush_and_reload() {
+        let backend = LocalBackend::in_memory();
+        block_on(async {
+            let mut kv = GamploKv::load(&backend, [1, 2]).await.unwrap();
+            kv.set("high_score", &1200).unwrap();
+            kv.set("settings", &json!({"volume": 0.5})).unwrap();
+            kv.set("levels", &["1-1", "1-2"]).unwrap();
+            assert!(kv.is_dirty());
+            kv.flush().await.unwrap();
+            assert!(!kv.is_dirty());
+
+            let mut kv = GamploKv::load(&backend, [1, 2]).await.unwrap();
+            assert_eq!(kv.get_as::<u32>("high_score").unwrap(), Some(1200));
+            assert_eq!(
+                kv.get_as::<Vec<String>>("levels").unwrap().unwrap(),
+                ["1-1", "1-2"]
+            );
+            assert!(kv.get_as::<String>("high_score").is_err());
+            assert_eq!(kv.keys().count(), 3);
+
+            assert_eq!(kv.remove("high_score"), Some(json!(1200)));
+            kv.flush().await.unwrap();
+            let kv = GamploKv::load(&backend, [1, 2]).await.unwrap();
+            assert!(!kv.contains_key("high_score"));
+            assert!(kv.contains_key("settings"));
+        });
+    }
+
+    #[test]
+    fn rejects_non_object_slots() {
+        let backend = LocalBackend::in_memory();
+        block_on(async {
+            backend.save(Some(1), json!([1, 2, 3])).await.unwrap();
+            assert!(matches!(
+                GamploKv::load(&backend, [1]).await,
+                Err(GamploError::SaveCorrupted { slot: 1, .. })
+            ));
+            assert!(matches!(
+                GamploKv::load(&backend, []).await,
+                Err(GamploError::SlotConfig(_))
+            ));
+            assert!(matches!(
+                GamploKv::load(&backend, [2, 3, 2]).await,
+                Err(GamploError::SlotConfig(_))
+            ));
+        });
+    }
+}
@@ -1,12 +1,13 @@
 //! Gamplo SDK for Rust
-//! 
+//!
 //! Provides a Rust interface for the Gamplo API.
 //! Based on [Gamplo's JavaScript SDK](https://gamplo.com/developer/sdk) and is designed to be used in both server-side and client-side (WASM) Gamplo games.
 //! For more information/examples, [read the SDK documentation](https://gamplo.com/developer/sdk)
-//! 
+//!
 //! # Features
 //! - `client`: Enables client-side (WASM) functionality
 //! - `server`: Enables server-side functionality
+//! - `schemars`: Enables deriving save schemas from Rust types
 
 #[cfg(all(feature = "client", feature = "server"))]
 compile_error!("feature \"client\" and feature \"server\" cannot be enabled at the same time");
@@ -14,26 +15,45 @@ compile_error!("feature \"client\" and feature \"server\" cannot be enabled at t
 compile_error!("either feature \"client\" or feature \"server\" must be enabled");
 
 pub mod achievement;
+pub mod backend;
 pub mod error;
+pub mod kv;
+pub mod local;
+pub mod migration;
+pub mod moderation;
 pub mod player;
 pub mod save;
+pub mod storage;
 pub mod util;
 
+use std::sync::Arc;
+
 use error::GamploError;
 use serde_json::json;
 #[cfg(feature = "client")]
 use web_sys::{js_sys::Reflect, wasm_bindgen::JsValue};
 
 use crate::{
-    achievement::{Achievement, AchievementUnlockResponse},
+    achievement::{Achievement, AchievementUnlockResponse, localization::AchievementLocalization},
+    moderation::{ModerationCache, filter::ModerationFilter, report::ModerationReport},
     player::Player,
-    save::{SaveData, SaveWriteResponse, Saves},
+    save::{
+        SaveData, SaveWriteResponse, Saves,
+        binary::SaveFormat,
+        codec::{SaveCodec, SaveIntegrity},
+        encryption::SaveEncryption,
+        header::SaveHeader,
+        schema::SaveSchema,
+    },
     util::get_error,
 };
 
 /// The URL for gamplo.com.
 pub const GAMPLO_URL: &str = "https://gamplo.com";
 
+/// The most bytes browsers allow in the bodies of in-flight keepalive requests, in total.
+pub const KEEPALIVE_BODY_LIMIT: usize = 64 * 1024;
+
 fn evaluate_url_path(path: &str) -> String {
     format!("{}{}", GAMPLO_URL, path)
 }
@@ -43,6 +63,11 @@ fn evaluate_url_path(path: &str) -> String {
 pub struct Gamplo {
     session_id: String,
     client: reqwest::Client,
+    localization: Option<Arc<AchievementLocalization>>,
+    save_codec: SaveCodec,
+    moderation_cache: Option<Arc<ModerationCache>>,
+    moderation_filter: Option<Arc<ModerationFilter>>,
+    moderation_fail_closed: bool,
 }
 impl Gamplo {
     /// Creates a new Gamplo client from an authentication token.
@@ -91,11 +116,16 @@ impl Gamplo {
         let client_struct = Gamplo {
             session_id: parsed.session_id,
             client,
+            localization: None,
+            save_codec: SaveCodec::default(),
+            moderation_cache: None,
+            moderation_filter: None,
+            moderation_fail_closed: false,
         };
         Ok((client_struct, parsed.player))
     }
     /// Creates a new Gamplo client using the token stored in `window.GAMPLO_TOKEN`, if any.
-    /// 
+    ///
     /// For server use or for when you want to provide the token explicitly, use [`Gamplo::from_token`] or [`Gamplo::from_token_with_player`] instead.
     /// See also: [`get_token`] for getting the token from `window.GAMPLO_TOKEN` directly.
     #[cfg(feature = "client")]
@@ -104,7 +134,7 @@ impl Gamplo {
         Self::from_token(token).await
     }
     /// Creates a new Gamplo client using the token stored in `window.GAMPLO_TOKEN` (if any) and also returns the authenticated player if available.
-    /// 
+    ///
     /// For server use or for when you want to provide the token explicitly, use [`Gamplo::from_token_with_player`] instead.
     /// See also: [`get_token`] for getting the token from `window.GAMPLO_TOKEN` directly.
     #[cfg(feature = "client")]
@@ -163,7 +193,7 @@ impl Gamplo {
                     field: "achievements".to_string(),
                     response: format!("{:?}", value),
                 })?;
-        let achievements: Vec<achievement::Achievement> =
+        let mut achievements: Vec<achievement::Achievement> =
             serde_json::from_value(achievements_value.clone()).map_err(|err| {
                 GamploError::Deserialization {
                     type_name: "achievements".to_string(),
@@ -171,6 +201,11 @@ impl Gamplo {
                     source: err,
                 }
             })?;
+        if let Some(localization) = &self.localization {
+            achievements
+                .iter_mut()
+                .for_each(|a| localization.localize(a));
+        }
 
         Ok(achievements)
     }
@@ -192,6 +227,12 @@ impl Gamplo {
             })?;
         Ok(saves)
     }
+    /// Gets every save slot's data for this client, fetching at most `concurrency` slots at a time.
+    ///
+    /// Errors fetching a single slot are reported in that slot's entry instead of failing the whole call.
+    pub async fn get_all_saves(&self, concurrency: usize) -> Result<save::AllSaves, GamploError> {
+        save::get_all_saves(self, concurrency).await
+    }
     /// Gets a specific save slot for this client.
     pub async fn get_save(&self, slot: u32) -> Result<Option<SaveData>, GamploError> {
         let response = self
@@ -205,12 +246,14 @@ impl Gamplo {
             return Ok(None);
         }
         let text = response.text().await?;
-        let save: SaveData =
+        let mut save: SaveData =
             serde_json::from_str(&text).map_err(|err| GamploError::Deserialization {
                 type_name: "SaveData".to_string(),
                 data: text.clone(),
                 source: err,
             })?;
+        (save.header, save.data) = self.save_codec.decode_with_header(save.slot, save.data)?;
+        self.save_codec.validate(Some(save.slot), &save.data)?;
         Ok(Some(save))
     }
     /// Unlocks an achievement for this client.
@@ -241,7 +284,10 @@ impl Gamplo {
                 achievement, parsed
             )));
         }
-        let response: AchievementUnlockResponse = serde_json::from_value(parsed)?;
+        let mut response: AchievementUnlockResponse = serde_json::from_value(parsed)?;
+        if let Some(localization) = &self.localization {
+            localization.localize_lite(&mut response.achievement);
+        }
         Ok(response)
     }
     /// Unlocks an achievement for this client with an API secret. For use on the server only as the API secret should never be exposed to clients.
@@ -266,24 +312,40 @@ impl Gamplo {
                 achievement, parsed
             )));
         }
-        Ok(serde_json::from_value(parsed)?)
+        let mut response: AchievementUnlockResponse = serde_json::from_value(parsed)?;
+        if let Some(localization) = &self.localization {
+            localization.localize_lite(&mut response.achievement);
+        }
+        Ok(response)
     }
     /// Saves data to a specific slot for this client. If `slot` is `None`, it will save to the first available slot.
+    ///
+    /// The save is written without a header, removing any header the slot had. Use
+    /// [`Gamplo::save_with_header`] to keep one.
     pub async fn save(
         &self,
         slot: Option<u32>,
         data: serde_json::Value,
     ) -> Result<SaveWriteResponse, GamploError> {
-        let mut body = json!({ "data": data });
-        if let Some(s) = slot {
-            body["slot"] = serde_json::json!(s);
-        }
+        self.save_with_header(slot, None, data).await
+    }
+    /// Saves data like [`Gamplo::save`], along with a header describing the save for save-select screens.
+    ///
+    /// The header is returned in [`SaveData::header`] and can be listed for every slot with a [`save::header::SaveHeaderCache`].
+    /// The header replaces the slot's previous header, so saving with `None` removes it.
+    pub async fn save_with_header(
+        &self,
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        data: serde_json::Value,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        let body = self.save_body(slot, header, data)?;
         let text = self
             .client
             .post(evaluate_url_path("/api/sdk/saves"))
             .header("Content-Type", "application/json")
             .header("x-sdk-session", self.session_id.clone())
-            .body(body.to_string())
+            .body(body)
             .send()
             .await?
             .text()
@@ -296,6 +358,100 @@ impl Gamplo {
             })?;
         Ok(resp)
     }
+    /// Saves `value` serialized in `format`, e.g. MessagePack for large or binary game state.
+    /// See [`save::binary`].
+    pub async fn save_as<T: serde::Serialize + ?Sized>(
+        &self,
+        slot: Option<u32>,
+        value: &T,
+        format: SaveFormat,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        save::binary::save_as(self, slot, value, format).await
+    }
+    /// Gets a specific save slot and deserializes it as `T`, whichever [`SaveFormat`] it was saved in.
+    pub async fn get_save_as<T: serde::de::DeserializeOwned>(
+        &self,
+        slot: u32,
+    ) -> Result<Option<T>, GamploError> {
+        save::binary::get_save_as(self, slot).await
+    }
+    /// Saves data like [`Gamplo::save`], but with `fetch(..., { keepalive: true })` so the request
+    /// completes even if the page is being unloaded. The response is not awaited.
+    #[cfg(all(feature = "client", target_arch = "wasm32"))]
+    pub fn save_keepalive(
+        &self,
+        slot: Option<u32>,
+        data: serde_json::Value,
+    ) -> Result<(), GamploError> {
+        self.save_keepalive_with_header(slot, None, data)
+    }
+    /// Saves data and a header like [`Gamplo::save_with_header`], but with keepalive like
+    /// [`Gamplo::save_keepalive`].
+    ///
+    /// Browsers drop keepalive requests with bodies over [`KEEPALIVE_BODY_LIMIT`], so larger saves
+    /// fail with [`GamploError::KeepaliveTooLarge`] instead of being sent.
+    #[cfg(all(feature = "client", target_arch = "wasm32"))]
+    pub fn save_keepalive_with_header(
+        &self,
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        data: serde_json::Value,
+    ) -> Result<(), GamploError> {
+        let mut budget = KEEPALIVE_BODY_LIMIT;
+        self.send_keepalive(slot, header, data, &mut budget)
+    }
+    /// Sends a keepalive save if its body fits in `budget`, and takes its size out of the budget.
+    #[cfg(all(feature = "client", target_arch = "wasm32"))]
+    pub(crate) fn send_keepalive(
+        &self,
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        data: serde_json::Value,
+        budget: &mut usize,
+    ) -> Result<(), GamploError> {
+        let body = self.save_body(slot, header, data)?;
+        if body.len() > *budget {
+            return Err(GamploError::KeepaliveTooLarge {
+                size: body.len(),
+                remaining: *budget,
+            });
+        }
+        let to_error = |e| GamploError::Wasm(format!("Failed to send keepalive save: {:?}", e));
+        let window = web_sys::window()
+            .ok_or_else(|| GamploError::Wasm(String::from("Failed to get window object")))?;
+        let headers = web_sys::Headers::new().map_err(to_error)?;
+        headers
+            .set("Content-Type", "application/json")
+            .map_err(to_error)?;
+        headers
+            .set("x-sdk-session", &self.session_id)
+            .map_err(to_error)?;
+        let init = web_sys::RequestInit::new();
+        init.set_method("POST");
+        init.set_headers(&headers);
+        init.set_body(&JsValue::from_str(&body));
+        Reflect::set(&init, &JsValue::from_str("keepalive"), &JsValue::TRUE).map_err(to_error)?;
+        let request =
+            web_sys::Request::new_with_str_and_init(&evaluate_url_path("/api/sdk/saves"), &init)
+                .map_err(to_error)?;
+        let _ = window.fetch_with_request(&request);
+        *budget -= body.len();
+        Ok(())
+    }
+    /// Builds the request body for [`Gamplo::save`].
+    fn save_body(
+        &self,
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        data: serde_json::Value,
+    ) -> Result<String, GamploError> {
+        self.save_codec.validate(slot, &data)?;
+        let mut body = json!({ "data": self.save_codec.encode_with_header(slot, header, data)? });
+        if let Some(s) = slot {
+            body["slot"] = serde_json::json!(s);
+        }
+        Ok(body.to_string())
+    }
     /// Deletes a save slot for this client.
     pub async fn delete_save(&self, slot: u32) -> Result<save::SaveDeleteResponse, GamploError> {
         let text = self
@@ -316,9 +472,46 @@ impl Gamplo {
         Ok(resp)
     }
     /// Moderates text for this client. Returns whether the text is allowed or blocked, and if blocked, the reason why.
+    ///
+    /// Texts are checked against the local filter first, if any (see [`Gamplo::with_moderation_filter`]),
+    /// and server results are cached if a cache was added with [`Gamplo::with_moderation_cache`].
     pub async fn moderate(&self, text: &str) -> Result<ModerationResult, GamploError> {
+        if let Some(result) = self
+            .moderation_filter
+            .as_ref()
+            .and_then(|filter| filter.check(text).result())
+        {
+            return Ok(result);
+        }
+        if let Some(result) = self.moderation_cache().and_then(|cache| cache.get(text)) {
+            return Ok(result);
+        }
+        let result = self.request_moderation(text).await?.result;
+        if let Some(cache) = self.moderation_cache() {
+            cache.insert(text, result.clone());
+        }
+        Ok(result)
+    }
+    /// Like [`Gamplo::moderate`], but returns the full response, including categories, scores and
+    /// flagged spans when the server sends them. Always sends a request unless the local filter
+    /// decides the text.
+    pub async fn moderate_detailed(&self, text: &str) -> Result<ModerationReport, GamploError> {
+        if let Some(result) = self
+            .moderation_filter
+            .as_ref()
+            .and_then(|filter| filter.check(text).result())
+        {
+            return Ok(result.into());
+        }
+        let report = self.request_moderation(text).await?;
+        if let Some(cache) = self.moderation_cache() {
+            cache.insert(text, report.result.clone());
+        }
+        Ok(report)
+    }
+    async fn request_moderation(&self, text: &str) -> Result<ModerationReport, GamploError> {
         let body = json!({ "text": text }).to_string();
-        let text = self
+        let response = self
             .client
             .post(evaluate_url_path("/api/sdk/moderate"))
             .header("Content-Type", "application/json")
@@ -328,25 +521,103 @@ impl Gamplo {
             .await?
             .text()
             .await?;
-        let resp = {
-            let parsed: serde_json::Value = serde_json::from_str(&text)?;
-            if parsed.get("blocked").and_then(|v| v.as_bool()) == Some(true) {
-                ModerationResult::Blocked {
-                    reason: parsed
-                        .get("reason")
-                        .and_then(|v| v.as_str())
-                        .map(|s| s.to_string()),
-                }
-            } else {
-                ModerationResult::Allowed
-            }
-        };
-        Ok(resp)
+        ModerationReport::parse(&response, self.moderation_fail_closed)
+    }
+    /// Moderates every text of `texts`, sending at most `concurrency` requests at a time
+    /// (see [`moderation::DEFAULT_MODERATION_CONCURRENCY`]), and returns the results in input order.
+    ///
+    /// Texts that are cached or repeated in `texts` are not sent again. If any text fails to be
+    /// moderated, the whole batch fails with that error and the other results are discarded.
+    pub async fn moderate_many(
+        &self,
+        texts: &[&str],
+        concurrency: usize,
+    ) -> Result<Vec<ModerationResult>, GamploError> {
+        moderation::moderate_many(self, texts, concurrency).await
+    }
+    /// Overrides achievement titles and descriptions returned by this client with client-side translations.
+    ///
+    /// The active locale can be changed later through [`Gamplo::localization`].
+    pub fn with_localization(mut self, localization: AchievementLocalization) -> Self {
+        self.localization = Some(Arc::new(localization));
+        self
+    }
+    /// Returns the achievement localization attached with [`Gamplo::with_localization`], if any.
+    pub fn localization(&self) -> Option<&AchievementLocalization> {
+        self.localization.as_deref()
+    }
+    /// Adds an integrity check to saves written by [`Gamplo::save`], which [`Gamplo::get_save`]
+    /// verifies, returning [`GamploError::SaveCorrupted`] or [`GamploError::SaveTampered`] on failure.
+    ///
+    /// Saves without an integrity tag fail too, see [`Gamplo::with_legacy_saves`]. The check is
+    /// bound to the slot, so checked saves must be written to an explicit slot.
+    pub fn with_save_integrity(mut self, integrity: SaveIntegrity) -> Self {
+        self.save_codec = self.save_codec.with_integrity(integrity);
+        self
+    }
+    /// Lets [`Gamplo::get_save`] return saves written before integrity checks were configured.
+    /// See [`SaveCodec::with_legacy_saves`].
+    pub fn with_legacy_saves(mut self, allow: bool) -> Self {
+        self.save_codec = self.save_codec.with_legacy_saves(allow);
+        self
+    }
+    /// Encrypts save data written by [`Gamplo::save`], which [`Gamplo::get_save`] decrypts,
+    /// returning [`GamploError::SaveDecryption`] if none of the keys fit or the save is not encrypted.
+    ///
+    /// Encrypted saves are bound to their slot, so [`Gamplo::save`] needs an explicit slot.
+    pub fn with_save_encryption(mut self, encryption: SaveEncryption) -> Self {
+        self.save_codec = self.save_codec.with_encryption(encryption);
+        self
+    }
+    /// Requires data written to and read from `slot` to match `schema`. [`Gamplo::save`] refuses
+    /// and [`Gamplo::get_save`] rejects data that does not, with [`GamploError::SaveValidation`].
+    pub fn with_save_schema(mut self, slot: u32, schema: SaveSchema) -> Self {
+        self.save_codec = self.save_codec.with_schema(slot, schema);
+        self
+    }
+    /// Like [`Gamplo::with_save_schema`], for slots without a schema of their own and saves without a slot.
+    pub fn with_default_save_schema(mut self, schema: SaveSchema) -> Self {
+        self.save_codec = self.save_codec.with_default_schema(schema);
+        self
+    }
+    /// Caches [`Gamplo::moderate`] results in `cache`, e.g. `ModerationCache::default()`.
+    /// Results aren't cached unless this is set.
+    ///
+    /// Texts that only differ in case or whitespace share a cached result, see [`ModerationCache`].
+    pub fn with_moderation_cache(mut self, cache: ModerationCache) -> Self {
+        self.moderation_cache = Some(Arc::new(cache));
+        self
+    }
+    pub fn moderation_cache(&self) -> Option<&ModerationCache> {
+        self.moderation_cache.as_deref()
+    }
+    /// Checks texts against `filter` before sending them to [`Gamplo::moderate`], so obvious cases
+    /// are decided locally. See [`ModerationFilter::metrics`] for how often each path was taken.
+    pub fn with_moderation_filter(mut self, filter: ModerationFilter) -> Self {
+        self.moderation_filter = Some(Arc::new(filter));
+        self
+    }
+    pub fn moderation_filter(&self) -> Option<&ModerationFilter> {
+        self.moderation_filter.as_deref()
+    }
+    /// Makes moderation fail with [`GamploError::MissingField`] when a response has no `blocked`
+    /// field, instead of treating the text as allowed.
+    pub fn with_moderation_fail_closed(mut self, fail_closed: bool) -> Self {
+        self.moderation_fail_closed = fail_closed;
+        self
+    }
+    /// Returns the codec applied to save data by [`Gamplo::save`] and [`Gamplo::get_save`].
+    pub fn save_codec(&self) -> &SaveCodec {
+        &self.save_codec
     }
     /// Returns the session ID for this client.
     pub fn session_id(&self) -> &str {
         &self.session_id
     }
+    /// Returns the HTTP client used for requests, so related downloads share its configuration.
+    pub(crate) fn http_client(&self) -> &reqwest::Client {
+        &self.client
+    }
 }
 
 /// Represents the result of a moderation check.
@@ -379,10 +650,15 @@ impl ModerationResult {
 /// Use [`Gamplo::from_token`] to provide a token explicitly.
 #[cfg(feature = "client")]
 pub fn get_token() -> Result<String, GamploError> {
-    let window = web_sys::window().ok_or_else(|| GamploError::TokenNotFound(String::from("Failed to get window object")))?;
-    let url = Reflect::get(&window, &JsValue::from_str("GAMPLO_TOKEN")).map_err(|_| GamploError::TokenNotFound(String::from("Failed to access GAMPLO_TOKEN from window")))?;
+    let window = web_sys::window()
+        .ok_or_else(|| GamploError::TokenNotFound(String::from("Failed to get window object")))?;
+    let url = Reflect::get(&window, &JsValue::from_str("GAMPLO_TOKEN")).map_err(|_| {
+        GamploError::TokenNotFound(String::from("Failed to access GAMPLO_TOKEN from window"))
+    })?;
     if url.is_undefined() {
-        return Err(GamploError::TokenNotFound(String::from("GAMPLO_TOKEN is not defined on the window object")));
+        return Err(GamploError::TokenNotFound(String::from(
+            "GAMPLO_TOKEN is not defined on the window object",
+        )));
     }
     let token = url.as_string().unwrap();
     Ok(token)
@@ -401,6 +677,7 @@ mod tests {
             data: serde_json::json!({"foo": "bar"}),
             size_bytes: 123,
             updated_at: chrono::Utc::now(),
+            header: None,
         };
         let serialized = serde_json::to_string(&save).unwrap();
         println!("Serialized save: {}", serialized);
@@ -420,7 +697,8 @@ mod tests {
             points: 10,
             hidden: false,
             unlocked: true,
-            unlocked_at: chrono::Utc::now(),
+            unlocked_at: Some(chrono::Utc::now()),
+            extra: Default::default(),
         };
         let serialized = serde_json::to_string(&achievement).unwrap();
         println!("Serialized achievement: {}", serialized);
@@ -429,6 +707,51 @@ mod tests {
         assert_eq!(achievement, deserialized);
     }
 
+    #[test]
+    fn achievements_fixture() {
+        #[derive(serde::Deserialize)]
+        struct AchievementsResponse {
+            achievements: Vec<achievement::Achievement>,
+        }
+
+        let parsed: AchievementsResponse =
+            serde_json::from_str(include_str!("../fixtures/achievements.json")).unwrap();
+        let [unlocked, locked, hidden_locked, hidden_unlocked] = &parsed.achievements[..] else {
+            panic!("expected 4 achievements, got {:?}", parsed.achievements);
+        };
+
+        assert!(unlocked.unlocked());
+        assert_eq!(
+            unlocked.unlocked_at().unwrap().to_rfc3339(),
+            "2026-02-14T18:03:27.512+00:00"
+        );
+
+        assert!(!locked.unlocked());
+        assert!(locked.unlocked_at().is_none());
+
+        assert!(hidden_locked.hidden());
+        assert!(!hidden_locked.unlocked());
+        assert!(hidden_locked.unlocked_at().is_none());
+        assert_eq!(hidden_locked.description(), "");
+        assert_eq!(hidden_locked.icon_url(), "");
+
+        assert!(hidden_unlocked.hidden());
+        assert!(hidden_unlocked.unlocked_at().is_some());
+        assert_eq!(hidden_unlocked.extra()["rarity"], serde_json::json!(0.02));
+        assert!(hidden_unlocked.extra().contains_key("createdAt"));
+    }
+
+    #[test]
+    fn achievement_unlock_fixture() {
+        let parsed: AchievementUnlockResponse =
+            serde_json::from_str(include_str!("../fixtures/achievement_unlock.json")).unwrap();
+        assert!(parsed.success());
+        assert!(!parsed.already_unlocked());
+        assert_eq!(parsed.achievement().key(), "first_blood");
+        assert_eq!(parsed.achievement().points(), 10);
+        assert!(parsed.achievement().extra().contains_key("unlockedAt"));
+    }
+
     #[test]
     fn auth_player_nullable() {
         #[derive(serde::Deserialize)]
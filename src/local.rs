@@ -0,0 +1,534 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    ModerationResult,
+    achievement::{Achievement, AchievementLite, AchievementUnlockResponse},
+    backend::GamploBackend,
+    error::GamploError,
+    player::Player,
+    save::{
+        SaveData, SaveDeleteResponse, SaveMetadata, SaveWriteResponse, Saves, header::SaveHeader,
+    },
+    storage::{MemoryStorage, Storage},
+};
+
+/// Number of save slots a [`LocalBackend`] offers by default.
+pub const DEFAULT_MAX_SLOTS: u32 = 10;
+/// Maximum size of a single save a [`LocalBackend`] accepts by default.
+pub const DEFAULT_MAX_SIZE_BYTES: u64 = 1024 * 1024;
+
+const PLAYER_KEY: &str = "local:player";
+const ACHIEVEMENTS_KEY: &str = "local:achievements";
+const SAVE_KEY_PREFIX: &str = "local:save:";
+/// The title Gamplo shows in place of a hidden achievement that is still locked.
+const HIDDEN_TITLE: &str = "???";
+
+/// Definition of an achievement offered by a [`LocalBackend`], mirroring what is configured on Gamplo.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AchievementDefinition {
+    pub key: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "icon", default)]
+    pub icon_url: String,
+    #[serde(default)]
+    pub points: u32,
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+/// How a save slot is kept in [`Storage`].
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredSave {
+    data: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    header: Option<SaveHeader>,
+    size_bytes: u64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+/// A [`GamploBackend`] that works without Gamplo, for itch.io builds, local development and
+/// players who are not logged in.
+///
+/// Achievements come from a list of [`AchievementDefinition`]s, saves are limited to
+/// `max_slots` slots (numbered from 1) of at most `max_size_bytes` each, and the player is a guest.
+/// Everything is persisted through a [`Storage`]. Moderation always allows text.
+#[derive(Debug)]
+pub struct LocalBackend<S = MemoryStorage> {
+    storage: S,
+    definitions: Vec<AchievementDefinition>,
+    max_slots: u32,
+    max_size_bytes: u64,
+}
+impl LocalBackend<MemoryStorage> {
+    /// Creates a local backend that keeps everything in memory.
+    pub fn in_memory() -> Self {
+        Self::new(MemoryStorage::new())
+    }
+}
+impl<S: Storage> LocalBackend<S> {
+    /// Creates a local backend persisted in `storage`, with no achievements and the default save limits.
+    pub fn new(storage: S) -> Self {
+        Self {
+            storage,
+            definitions: Vec::new(),
+            max_slots: DEFAULT_MAX_SLOTS,
+            max_size_bytes: DEFAULT_MAX_SIZE_BYTES,
+        }
+    }
+    /// Sets the achievements offered by this backend.
+    pub fn with_achievements(mut self, definitions: Vec<AchievementDefinition>) -> Self {
+        self.definitions = definitions;
+        self
+    }
+    /// Sets the save limits enforced by this backend.
+    pub fn with_limits(mut self, max_slots: u32, max_size_bytes: u64) -> Self {
+        self.max_slots = max_slots;
+        self.max_size_bytes = max_size_bytes;
+        self
+    }
+    /// Replaces the stored player, e.g. to give the guest a chosen name.
+    pub fn set_player(&self, player: &Player) -> Result<(), GamploError> {
+        self.storage
+            .set(PLAYER_KEY, &serde_json::to_string(player)?)
+    }
+    /// Returns the storage this backend persists to.
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Returns the guest player, creating and persisting one on first use.
+    fn player(&self) -> Result<Player, GamploError> {
+        if let Some(player) = self.read::<Player>(PLAYER_KEY)? {
+            return Ok(player);
+        }
+        let now = Utc::now();
+        let player = Player {
+            id: format!(
+                "guest-{:x}",
+                now.timestamp_nanos_opt()
+                    .unwrap_or_else(|| now.timestamp_millis())
+            ),
+            username: "guest".to_string(),
+            display_name: "Guest".to_string(),
+            avatar_url: None,
+        };
+        self.set_player(&player)?;
+        Ok(player)
+    }
+    fn unlocks(&self) -> Result<BTreeMap<String, DateTime<Utc>>, GamploError> {
+        Ok(self.read(ACHIEVEMENTS_KEY)?.unwrap_or_default())
+    }
+    fn stored_save(&self, slot: u32) -> Result<Option<StoredSave>, GamploError> {
+        self.read(&save_key(slot))
+    }
+    fn read<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>, GamploError> {
+        let Some(text) = self.storage.get(key)? else {
+            return Ok(None);
+        };
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| GamploError::Deserialization {
+                type_name: key.to_string(),
+                data: text,
+                source: e,
+            })
+    }
+    fn check_slot(&self, slot: u32) -> Result<(), GamploError> {
+        if slot == 0 || slot > self.max_slots {
+            return Err(GamploError::InvalidSlot {
+                slot,
+                max_slots: self.max_slots,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<S: Storage> GamploBackend for LocalBackend<S> {
+    async fn get_player(&self) -> Result<Option<Player>, GamploError> {
+        self.player().map(Some)
+    }
+    async fn get_achievements(&self) -> Result<Vec<Achievement>, GamploError> {
+        let unlocks = self.unlocks()?;
+        Ok(self
+            .definitions
+            .iter()
+            .enumerate()
+            .map(|(index, definition)| {
+                let unlocked_at = unlocks.get(&definition.key).copied();
+                // Like Gamplo, conceal hidden achievements until they are unlocked.
+                let concealed = definition.hidden && unlocked_at.is_none();
+                let reveal = |value: &String| match concealed {
+                    true => String::new(),
+                    false => value.clone(),
+                };
+                Achievement {
+                    id: index as u32 + 1,
+                    key: definition.key.clone(),
+                    title: match concealed {
+                        true => HIDDEN_TITLE.to_string(),
+                        false => definition.title.clone(),
+                    },
+                    description: reveal(&definition.description),
+                    icon_url: reveal(&definition.icon_url),
+                    points: definition.points,
+                    hidden: definition.hidden,
+                    unlocked: unlocked_at.is_some(),
+                    unlocked_at,
+                    extra: Default::default(),
+                }
+            })
+            .collect())
+    }
+    async fn unlock_achievement(
+        &self,
+        achievement: &str,
+    ) -> Result<AchievementUnlockResponse, GamploError> {
+        let definition = self
+            .definitions
+            .iter()
+            .find(|d| d.key == achievement)
+            .ok_or_else(|| {
+                GamploError::ApiError(format!(
+                    "Failed to unlock achievement: {}, response: unknown achievement",
+                    achievement
+                ))
+            })?;
+        let mut unlocks = self.unlocks()?;
+        let already_unlocked = unlocks.contains_key(achievement);
+        if !already_unlocked {
+            unlocks.insert(achievement.to_string(), Utc::now());
+            self.storage
+                .set(ACHIEVEMENTS_KEY, &serde_json::to_string(&unlocks)?)?;
+        }
+        Ok(AchievementUnlockResponse {
+            success: true,
+            already_unlocked,
+            achievement: AchievementLite {
+                key: definition.key.clone(),
+                title: definition.title.clone(),
+                description: definition.description.clone(),
+                icon_url: definition.icon_url.clone(),
+                points: definition.points,
+                extra: Default::default(),
+            },
+        })
+    }
+    async fn get_saves(&self) -> Result<Saves, GamploError> {
+        let mut saves = Vec::new();
+        for slot in 1..=self.max_slots {
+            if let Some(save) = self.stored_save(slot)? {
+                saves.push(SaveMetadata {
+                    slot,
+                    size_bytes: save.size_bytes,
+                    created_at: save.created_at,
+                    updated_at: save.updated_at,
+                });
+            }
+        }
+        Ok(Saves {
+            saves,
+            max_slots: self.max_slots,
+            max_size_bytes: self.max_size_bytes,
+        })
+    }
+    async fn get_save(&self, slot: u32) -> Result<Option<SaveData>, GamploError> {
+        self.check_slot(slot)?;
+        Ok(self.stored_save(slot)?.map(|save| SaveData {
+            slot,
+            data: save.data,
+            size_bytes: save.size_bytes,
+            updated_at: save.updated_at,
+            header: save.header,
+        }))
+    }
+    async fn save(&self, slot: Option<u32>, data: Value) -> Result<SaveWriteResponse, GamploError> {
+        self.save_with_header(slot, None, data).await
+    }
+    async fn save_with_header(
+        &self,
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        data: Value,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        let slot = match slot {
+            Some(slot) => slot,
+            None => {
+                let mut free = None;
+                for slot in 1..=self.max_slots {
+                    if self.stored_save(slot)?.is_none() {
+                        free = Some(slot);
+                        break;
+                    }
+                }
+                free.ok_or_else(|| {
+                    GamploError::ApiError(format!("All {} save slots are in use", self.max_slots))
+                })?
+            }
+        };
+        self.check_slot(slot)?;
+        let size_bytes = serde_json::to_string(&data)?.len() as u64;
+        if size_bytes > self.max_size_bytes {
+            return Err(GamploError::ApiError(format!(
+                "Save data is {} bytes, the maximum is {} bytes",
+                size_bytes, self.max_size_bytes
+            )));
+        }
+        let now = Utc::now();
+        let created_at = self
+            .stored_save(slot)?
+            .map(|save| save.created_at)
+            .unwrap_or(now);
+        let stored = StoredSave {
+            data,
+            header: header.cloned(),
+            size_bytes,
+            created_at,
+            updated_at: now,
+        };
+        self.storage
+            .set(&save_key(slot), &serde_json::to_string(&stored)?)?;
+        Ok(SaveWriteResponse {
+            success: true,
+            slot,
+            size_bytes,
+            updated_at: now,
+        })
+    }
+    async fn delete_save(&self, slot: u32) -> Result<SaveDeleteResponse, GamploError> {
+        self.check_slot(slot)?;
+        let deleted = self.stored_save(slot)?.is_some();
+        self.storage.remove(&save_key(slot))?;
+        Ok(SaveDeleteResponse {
+            success: true,
+            deleted,
+        })
+    }
+    async fn moderate(&self, _text: &str) -> Result<ModerationResult, GamploError> {
+        Ok(ModerationResult::Allowed)
+    }
+}
+
+fn save_key(slot: u32) -> String {
+    format!("{}{}", SAVE_KEY_PREFIX, slot)
+}
+
+/// A [`LocalBackend`] for tests that can be told to fail on some slots or to moderate differently.
+#[cfg(test)]
+#[derive(Debug)]
+pub(crate) struct TestBackend {
+    pub local: LocalBackend,
+    failing_reads: std::sync::Mutex<std::collections::HashSet<u32>>,
+    failing_writes: std::sync::Mutex<std::collections::HashSet<u32>>,
+    corrupted: std::sync::Mutex<std::collections::HashSet<u32>>,
+    moderation: Option<fn(&str) -> ModerationResult>,
+}
+#[cfg(test)]
+impl TestBackend {
+    pub fn new() -> Self {
+        Self {
+            local: LocalBackend::in_memory(),
+            failing_reads: Default::default(),
+            failing_writes: Default::default(),
+            corrupted: Default::default(),
+            moderation: None,
+        }
+    }
+    /// Moderates texts with `moderation` instead of allowing everything.
+    pub fn with_moderation(mut self, moderation: fn(&str) -> ModerationResult) -> Self {
+        self.moderation = Some(moderation);
+        self
+    }
+    /// Makes reading `slot` fail.
+    pub fn fail_reads(&self, slot: u32) {
+        self.failing_reads.lock().unwrap().insert(slot);
+    }
+    /// Makes writing `slot` fail.
+    pub fn fail_writes(&self, slot: u32) {
+        self.failing_writes.lock().unwrap().insert(slot);
+    }
+    /// Makes reading `slot` fail as if its data could not be decoded.
+    pub fn corrupt(&self, slot: u32) {
+        self.corrupted.lock().unwrap().insert(slot);
+    }
+    /// Makes reading and writing `slot` work again.
+    pub fn heal(&self, slot: u32) {
+        self.failing_reads.lock().unwrap().remove(&slot);
+        self.failing_writes.lock().unwrap().remove(&slot);
+        self.corrupted.lock().unwrap().remove(&slot);
+    }
+    fn check_write(&self, slot: Option<u32>) -> Result<(), GamploError> {
+        match slot {
+            Some(slot) if self.failing_writes.lock().unwrap().contains(&slot) => Err(
+                GamploError::ApiError(format!("slot {} is unwritable", slot)),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+#[cfg(test)]
+impl GamploBackend for TestBackend {
+    async fn get_player(&self) -> Result<Option<Player>, GamploError> {
+        self.local.get_player().await
+    }
+    async fn get_achievements(&self) -> Result<Vec<Achievement>, GamploError> {
+        self.local.get_achievements().await
+    }
+    async fn unlock_achievement(
+        &self,
+        achievement: &str,
+    ) -> Result<AchievementUnlockResponse, GamploError> {
+        self.local.unlock_achievement(achievement).await
+    }
+    async fn get_saves(&self) -> Result<Saves, GamploError> {
+        self.local.get_saves().await
+    }
+    async fn get_save(&self, slot: u32) -> Result<Option<SaveData>, GamploError> {
+        if self.failing_reads.lock().unwrap().contains(&slot) {
+            return Err(GamploError::ApiError(format!(
+                "slot {} is unreadable",
+                slot
+            )));
+        }
+        if self.corrupted.lock().unwrap().contains(&slot) {
+            return Err(GamploError::SaveCorrupted {
+                slot,
+                reason: "test corruption".to_string(),
+            });
+        }
+        self.local.get_save(slot).await
+    }
+    async fn save(&self, slot: Option<u32>, data: Value) -> Result<SaveWriteResponse, GamploError> {
+        self.check_write(slot)?;
+        self.local.save(slot, data).await
+    }
+    async fn save_with_header(
+        &self,
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        data: Value,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        self.check_write(slot)?;
+        self.local.save_with_header(slot, header, data).await
+    }
+    async fn delete_save(&self, slot: u32) -> Result<SaveDeleteResponse, GamploError> {
+        self.local.delete_save(slot).await
+    }
+    async fn moderate(&self, text: &str) -> Result<ModerationResult, GamploError> {
+        match self.moderation {
+            Some(moderation) => Ok(moderation(text)),
+            None => self.local.moderate(text).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::util::block_on;
+
+    fn backend() -> LocalBackend {
+        LocalBackend::in_memory()
+            .with_limits(2, 32)
+            .with_achievements(vec![AchievementDefinition {
+                key: "first_blood".to_string(),
+                title: "First Blood".to_string(),
+                description: String::new(),
+                icon_url: String::new(),
+                points: 10,
+                hidden: false,
+            }])
+    }
+
+    #[test]
+    fn achievements() {
+        let backend = backend();
+        let unlocked = block_on(backend.unlock_achievement("first_blood")).unwrap();
+        assert!(!unlocked.already_unlocked());
+        let again = block_on(backend.unlock_achievement("first_blood")).unwrap();
+        assert!(again.already_unlocked());
+        assert!(block_on(backend.unlock_achievement("missing")).is_err());
+
+        let achievements = block_on(backend.get_achievements()).unwrap();
+        assert_eq!(achievements.len(), 1);
+        assert!(achievements[0].unlocked());
+        assert!(achievements[0].unlocked_at().is_some());
+    }
+
+    #[test]
+    fn conceals_hidden_achievements() {
+        let backend = LocalBackend::in_memory().with_achievements(vec![AchievementDefinition {
+            key: "secret_ending".to_string(),
+            title: "Secret Ending".to_string(),
+            description: "Find the secret ending".to_string(),
+            icon_url: "https://example.com/secret.png".to_string(),
+            points: 25,
+            hidden: true,
+        }]);
+        let achievements = block_on(backend.get_achievements()).unwrap();
+        assert_eq!(achievements[0].title(), "???");
+        assert_eq!(achievements[0].description(), "");
+        assert_eq!(achievements[0].icon_url(), "");
+        assert_eq!(achievements[0].points(), 25);
+
+        block_on(backend.unlock_achievement("secret_ending")).unwrap();
+        let achievements = block_on(backend.get_achievements()).unwrap();
+        assert_eq!(achievements[0].title(), "Secret Ending");
+        assert_eq!(achievements[0].icon_url(), "https://example.com/secret.png");
+    }
+
+    #[test]
+    fn save_limits() {
+        let backend = backend();
+        assert_eq!(block_on(backend.save(None, json!(1))).unwrap().slot, 1);
+        assert_eq!(block_on(backend.save(None, json!(2))).unwrap().slot, 2);
+        assert!(block_on(backend.save(None, json!(3))).is_err());
+        assert!(matches!(
+            block_on(backend.save(Some(3), json!(3))),
+            Err(GamploError::InvalidSlot {
+                slot: 3,
+                max_slots: 2
+            })
+        ));
+        assert!(block_on(backend.save(Some(1), json!("x".repeat(64)))).is_err());
+
+        let saves = block_on(backend.get_saves()).unwrap();
+        assert_eq!(saves.saves.len(), 2);
+        assert_eq!(saves.max_slots, 2);
+        assert_eq!(
+            block_on(backend.get_save(2)).unwrap().unwrap().data,
+            json!(2)
+        );
+
+        assert!(block_on(backend.delete_save(1)).unwrap().deleted);
+        assert!(!block_on(backend.delete_save(1)).unwrap().deleted);
+        assert!(block_on(backend.get_save(1)).unwrap().is_none());
+        assert!(block_on(backend.get_save(0)).is_err());
+        assert!(block_on(backend.get_save(3)).is_err());
+        assert!(block_on(backend.delete_save(3)).is_err());
+    }
+
+    #[test]
+    fn guest_player_is_persisted() {
+        let storage = MemoryStorage::new();
+        let first = block_on(LocalBackend::new(&storage).get_player())
+            .unwrap()
+            .unwrap();
+        let second = block_on(LocalBackend::new(&storage).get_player())
+            .unwrap()
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.username, "guest");
+    }
+}
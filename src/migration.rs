@@ -0,0 +1,253 @@
+use std::collections::HashSet;
+
+use crate::{backend::GamploBackend, error::GamploError, save::SaveData};
+
+/// What to do when the account already has a save in a slot the guest also used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SaveConflictPolicy {
+    /// Keep the account's save and drop the guest's.
+    KeepAccount,
+    /// Overwrite the account's save with the guest's.
+    KeepGuest,
+    /// Keep whichever save was updated most recently.
+    #[default]
+    KeepNewest,
+    /// Keep the account's save and move the guest's into the first free account slot.
+    /// The guest save is not uploaded when no slot is free.
+    MoveGuestToFreeSlot,
+}
+
+/// What happened to one guest save slot during a migration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlotMigration {
+    /// The account had no save in this slot, so the guest save was uploaded.
+    Uploaded { slot: u32 },
+    /// The account's save was overwritten by the guest save.
+    Overwrote { slot: u32 },
+    /// The guest save was uploaded into a different, free slot.
+    Moved { from: u32, to: u32 },
+    /// The account's save was kept and the guest save was not uploaded.
+    KeptAccount { slot: u32 },
+    /// The guest save should have been moved, but the account has no free slot, so it was not uploaded.
+    NoFreeSlot { slot: u32 },
+    /// The guest slot doesn't exist on the account, which has fewer slots, so the guest save was
+    /// not uploaded. [`SaveConflictPolicy::MoveGuestToFreeSlot`] moves these saves instead.
+    OutOfRange { slot: u32 },
+}
+
+/// The outcome of [`migrate_guest_progress`].
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub slots: Vec<SlotMigration>,
+    /// Achievement keys newly unlocked on the account.
+    pub unlocked: Vec<String>,
+    /// Achievement keys that the account had already unlocked.
+    pub already_unlocked: Vec<String>,
+    /// Achievement keys that failed to unlock, e.g. because they don't exist on Gamplo.
+    pub failed: Vec<(String, GamploError)>,
+}
+
+/// Uploads a guest's progress (saves and unlocked achievements) into an authenticated account.
+///
+/// `guest` is typically a [`crate::local::LocalBackend`] the player used before logging in and
+/// `account` the [`crate::Gamplo`] client created once [`crate::Gamplo::from_token_with_player`]
+/// returns a player. Saves the account already has are resolved with `policy`.
+/// Failing to read the guest's progress or to upload a save aborts the migration,
+/// while achievements that fail to unlock are reported in [`MigrationReport::failed`].
+///
+/// The guest's progress is left as it is. Running the migration again uploads the guest saves
+/// again, which with [`SaveConflictPolicy::MoveGuestToFreeSlot`] duplicates them into more free
+/// slots, so delete the guest saves once the migration succeeds.
+pub async fn migrate_guest_progress(
+    guest: &impl GamploBackend,
+    account: &impl GamploBackend,
+    policy: SaveConflictPolicy,
+) -> Result<MigrationReport, GamploError> {
+    let mut report = MigrationReport::default();
+
+    let account_saves = account.get_saves().await?;
+    let mut used: HashSet<u32> = account_saves.saves.iter().map(|s| s.slot).collect();
+    let mut guest_saves = Vec::new();
+    for metadata in guest.get_saves().await?.saves {
+        if let Some(save) = guest.get_save(metadata.slot).await? {
+            guest_saves.push(save);
+        }
+    }
+    // Moved saves must not land in a slot another guest save is about to be uploaded to.
+    let reserved: HashSet<u32> = guest_saves.iter().map(|s| s.slot).collect();
+
+    for guest_save in guest_saves {
+        let slot = guest_save.slot;
+        let in_range = (1..=account_saves.max_slots).contains(&slot);
+        let free_slot =
+            || (1..=account_saves.max_slots).find(|s| !used.contains(s) && !reserved.contains(s));
+        let target = match policy {
+            SaveConflictPolicy::MoveGuestToFreeSlot if !in_range || used.contains(&slot) => {
+                match free_slot() {
+                    Some(to) => Ok((to, SlotMigration::Moved { from: slot, to })),
+                    None => Err(SlotMigration::NoFreeSlot { slot }),
+                }
+            }
+            _ if !in_range => Err(SlotMigration::OutOfRange { slot }),
+            _ if !used.contains(&slot) => Ok((slot, SlotMigration::Uploaded { slot })),
+            SaveConflictPolicy::KeepGuest => Ok((slot, SlotMigration::Overwrote { slot })),
+            SaveConflictPolicy::KeepNewest if newer_than_account(account, &guest_save).await? => {
+                Ok((slot, SlotMigration::Overwrote { slot }))
+            }
+            _ => Err(SlotMigration::KeptAccount { slot }),
+        };
+        match target {
+            Ok((to, outcome)) => {
+                account
+                    .save_with_header(Some(to), guest_save.header.as_ref(), guest_save.data)
+                    .await?;
+                used.insert(to);
+                report.slots.push(outcome);
+            }
+            Err(outcome) => report.slots.push(outcome),
+        }
+    }
+
+    for achievement in guest.get_achievements().await? {
+        if !achievement.unlocked() {
+            continue;
+        }
+        let key = achievement.key().to_string();
+        match account.unlock_achievement(&key).await {
+            Ok(response) if response.already_unlocked() => report.already_unlocked.push(key),
+            Ok(_) => report.unlocked.push(key),
+            Err(err) => report.failed.push((key, err)),
+        }
+    }
+
+    Ok(report)
+}
+
+async fn newer_than_account(
+    account: &impl GamploBackend,
+    guest_save: &SaveData,
+) -> Result<bool, GamploError> {
+    Ok(match account.get_save(guest_save.slot).await? {
+        Some(account_save) => guest_save.updated_at > account_save.updated_at,
+        None => true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        local::{AchievementDefinition, LocalBackend},
+        util::block_on,
+    };
+
+    fn backend() -> LocalBackend {
+        LocalBackend::in_memory()
+            .with_limits(3, 1024)
+            .with_achievements(
+                ["a", "b"]
+                    .map(|key| AchievementDefinition {
+                        key: key.to_string(),
+                        title: key.to_string(),
+                        description: String::new(),
+                        icon_url: String::new(),
+                        points: 0,
+                        hidden: false,
+                    })
+                    .to_vec(),
+            )
+    }
+
+    #[test]
+    fn migrates_saves_and_achievements() {
+        let guest = backend();
+        let account = backend();
+        block_on(async {
+            guest.save(Some(1), json!("guest 1")).await.unwrap();
+            guest.save(Some(2), json!("guest 2")).await.unwrap();
+            guest.unlock_achievement("a").await.unwrap();
+            guest.unlock_achievement("b").await.unwrap();
+            account.save(Some(1), json!("account 1")).await.unwrap();
+            account.unlock_achievement("b").await.unwrap();
+        });
+
+        let report = block_on(migrate_guest_progress(
+            &guest,
+            &account,
+            SaveConflictPolicy::MoveGuestToFreeSlot,
+        ))
+        .unwrap();
+        assert_eq!(
+            report.slots,
+            [
+                SlotMigration::Moved { from: 1, to: 3 },
+                SlotMigration::Uploaded { slot: 2 }
+            ]
+        );
+        assert_eq!(report.unlocked, ["a"]);
+        assert_eq!(report.already_unlocked, ["b"]);
+        assert!(report.failed.is_empty());
+
+        let data = |slot| block_on(account.get_save(slot)).unwrap().unwrap().data;
+        assert_eq!(data(1), json!("account 1"));
+        assert_eq!(data(2), json!("guest 2"));
+        assert_eq!(data(3), json!("guest 1"));
+    }
+
+    #[test]
+    fn keeps_newest() {
+        let guest = backend();
+        let account = backend();
+        block_on(async {
+            guest.save(Some(1), json!("guest")).await.unwrap();
+            account.save(Some(1), json!("account")).await.unwrap();
+        });
+        let report = block_on(migrate_guest_progress(
+            &guest,
+            &account,
+            SaveConflictPolicy::KeepNewest,
+        ))
+        .unwrap();
+        assert_eq!(report.slots, [SlotMigration::KeptAccount { slot: 1 }]);
+    }
+
+    #[test]
+    fn handles_out_of_range_and_full_accounts() {
+        let guest = LocalBackend::in_memory().with_limits(4, 1024);
+        let account = LocalBackend::in_memory().with_limits(2, 1024);
+        block_on(async {
+            guest.save(Some(1), json!("guest 1")).await.unwrap();
+            guest.save(Some(4), json!("guest 4")).await.unwrap();
+            account.save(Some(1), json!("account 1")).await.unwrap();
+        });
+        let report = block_on(migrate_guest_progress(
+            &guest,
+            &account,
+            SaveConflictPolicy::KeepGuest,
+        ))
+        .unwrap();
+        assert_eq!(
+            report.slots,
+            [
+                SlotMigration::Overwrote { slot: 1 },
+                SlotMigration::OutOfRange { slot: 4 }
+            ]
+        );
+
+        let report = block_on(migrate_guest_progress(
+            &guest,
+            &account,
+            SaveConflictPolicy::MoveGuestToFreeSlot,
+        ))
+        .unwrap();
+        assert_eq!(
+            report.slots,
+            [
+                SlotMigration::Moved { from: 1, to: 2 },
+                SlotMigration::NoFreeSlot { slot: 4 }
+            ]
+        );
+    }
+}
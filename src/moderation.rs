@@ -0,0 +1,167 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Mutex,
+};
+
+use futures_util::{StreamExt, stream};
+
+use crate::{ModerationResult, backend::GamploBackend, error::GamploError};
+
+pub mod filter;
+pub mod moderated;
+pub mod report;
+
+/// How many texts [`crate::save::moderation::SaveModerator`] moderates at once by default.
+pub const DEFAULT_MODERATION_CONCURRENCY: usize = 4;
+/// How many results a [`ModerationCache`] holds by default.
+pub const DEFAULT_MODERATION_CACHE_CAPACITY: usize = 512;
+
+/// Normalizes text for caching: trims it, collapses runs of whitespace and lowercases it,
+/// so `"Good Game"` and `" good  game "` share a moderation result.
+pub fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<String, (ModerationResult, u64)>,
+    /// Keys by when they were last used, oldest first.
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+/// A least-recently-used cache of moderation results, keyed by [`normalize`]d text.
+///
+/// Texts that only differ in case or whitespace share a result, so only cache results if the
+/// moderation service doesn't treat them differently.
+#[derive(Debug)]
+pub struct ModerationCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+}
+impl Default for ModerationCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MODERATION_CACHE_CAPACITY)
+    }
+}
+impl ModerationCache {
+    /// Creates a cache holding at most `capacity` results. A capacity of 0 disables caching.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lru: Mutex::new(Lru::default()),
+        }
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Gets the cached result for `text`, marking it as recently used.
+    pub fn get(&self, text: &str) -> Option<ModerationResult> {
+        let mut lru = self.lru();
+        let lru = &mut *lru;
+        let key = normalize(text);
+        let (result, used) = lru.entries.get_mut(&key)?;
+        lru.order.remove(used);
+        lru.tick += 1;
+        *used = lru.tick;
+        lru.order.insert(lru.tick, key);
+        Some(result.clone())
+    }
+    /// Caches `result` for `text`, evicting the least recently used result if the cache is full.
+    pub fn insert(&self, text: &str, result: ModerationResult) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lru = self.lru();
+        let key = normalize(text);
+        lru.tick += 1;
+        let tick = lru.tick;
+        if let Some((_, used)) = lru.entries.insert(key.clone(), (result, tick)) {
+            lru.order.remove(&used);
+        }
+        lru.order.insert(tick, key);
+        while lru.entries.len() > self.capacity {
+            let Some((_, oldest)) = lru.order.pop_first() else {
+                break;
+            };
+            lru.entries.remove(&oldest);
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.lru().entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn clear(&self) {
+        *self.lru() = Lru::default();
+    }
+
+    fn lru(&self) -> std::sync::MutexGuard<'_, Lru> {
+        // The cache is always left consistent, so a panic elsewhere doesn't invalidate it.
+        self.lru.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Moderates every text of `texts`, at most `concurrency` at a time, returning the results in input order.
+///
+/// Repeated texts are only sent once. Fails with the first error if any text fails.
+pub(crate) async fn moderate_many<B: GamploBackend + ?Sized>(
+    backend: &B,
+    texts: &[&str],
+    concurrency: usize,
+) -> Result<Vec<ModerationResult>, GamploError> {
+    let unique: HashSet<&str> = texts.iter().copied().collect();
+    let results: HashMap<&str, ModerationResult> = stream::iter(unique)
+        .map(|text| async move { Ok::<_, GamploError>((text, backend.moderate(text).await?)) })
+        .buffer_unordered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<_, _>>()?;
+    Ok(texts.iter().map(|text| results[text].clone()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{local::LocalBackend, util::block_on};
+
+    fn blocked(reason: &str) -> ModerationResult {
+        ModerationResult::Blocked {
+            reason: Some(reason.to_string()),
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ModerationCache::new(2);
+        cache.insert("Good  Game", ModerationResult::Allowed);
+        cache.insert("bad", blocked("rude"));
+        assert_eq!(cache.get(" good game"), Some(ModerationResult::Allowed));
+        cache.insert("other", ModerationResult::Allowed);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("bad"), None);
+        assert_eq!(cache.get("GOOD GAME"), Some(ModerationResult::Allowed));
+
+        let disabled = ModerationCache::new(0);
+        disabled.insert("gg", ModerationResult::Allowed);
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn moderates_many_in_order() {
+        let backend = LocalBackend::in_memory();
+        block_on(async {
+            let results = backend
+                .moderate_many(&["gg", "GG", "hello there"], 2)
+                .await
+                .unwrap();
+            assert_eq!(results, vec![ModerationResult::Allowed; 3]);
+            assert!(backend.moderate_many(&[], 2).await.unwrap().is_empty());
+        });
+    }
+}
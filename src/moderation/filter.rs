@@ -0,0 +1,258 @@
+use std::{
+    collections::HashSet,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use regex_lite::Regex;
+
+use crate::{ModerationResult, error::GamploError, moderation::normalize};
+
+/// What [`ModerationFilter::check`] decided about a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterVerdict {
+    /// The text was blocked locally, with the reason why.
+    Blocked(String),
+    /// The text is on the allowlist and doesn't need server moderation.
+    Allowed,
+    /// The filter has no opinion, so the text should go to the server.
+    Pass,
+}
+impl FilterVerdict {
+    /// The result this verdict short-circuits to, or `None` if the text should go to the server.
+    pub fn result(&self) -> Option<ModerationResult> {
+        match self {
+            FilterVerdict::Blocked(reason) => Some(ModerationResult::Blocked {
+                reason: Some(reason.clone()),
+            }),
+            FilterVerdict::Allowed => Some(ModerationResult::Allowed),
+            FilterVerdict::Pass => None,
+        }
+    }
+}
+
+/// How often each path of a [`ModerationFilter`] was taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FilterMetrics {
+    /// Texts blocked locally.
+    pub blocked: u64,
+    /// Texts allowed locally.
+    pub allowed: u64,
+    /// Texts passed through to the server.
+    pub passed: u64,
+}
+impl FilterMetrics {
+    pub fn total(&self) -> u64 {
+        self.blocked + self.allowed + self.passed
+    }
+}
+
+/// A local pre-filter run before server moderation, so obvious cases don't need a request.
+///
+/// Texts are [`fold`]ed before matching, so `"B4D W0RD"` and `"bаd word"` (with a Cyrillic `а`)
+/// both match a blocked `"bad word"`. Checks run in order: length limits, the allowlist,
+/// blocked words, then regex rules.
+///
+/// Add one to a client with [`crate::Gamplo::with_moderation_filter`].
+#[derive(Debug, Default)]
+pub struct ModerationFilter {
+    min_length: usize,
+    max_length: Option<usize>,
+    allowed: HashSet<String>,
+    blocked: Vec<String>,
+    rules: Vec<(Regex, String)>,
+    blocked_count: AtomicU64,
+    allowed_count: AtomicU64,
+    passed_count: AtomicU64,
+}
+impl ModerationFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Blocks texts with fewer than `min` characters, not counting surrounding whitespace.
+    pub fn with_min_length(mut self, min: usize) -> Self {
+        self.min_length = min;
+        self
+    }
+    /// Blocks texts with more than `max` characters, not counting surrounding whitespace.
+    pub fn with_max_length(mut self, max: usize) -> Self {
+        self.max_length = Some(max);
+        self
+    }
+    /// Allows `text` locally when a whole message folds to the same thing, e.g. `"gg"` or `"good game"`.
+    pub fn with_allowed(mut self, text: &str) -> Self {
+        self.allowed.insert(fold(text));
+        self
+    }
+    /// Blocks texts containing `word` as a whole word, or as a whole run of words if it has spaces.
+    pub fn with_blocked_word(mut self, word: &str) -> Self {
+        let word = fold(word);
+        if !word.is_empty() {
+            self.blocked.push(word);
+        }
+        self
+    }
+    pub fn with_blocked_words<'a>(self, words: impl IntoIterator<Item = &'a str>) -> Self {
+        words
+            .into_iter()
+            .fold(self, |filter, word| filter.with_blocked_word(word))
+    }
+    /// Blocks texts matching the regex `pattern`, with `reason` as the blocked reason.
+    ///
+    /// Rules match the text as written, not its folded form, so add `(?i)` for case-insensitive rules.
+    pub fn with_rule(mut self, pattern: &str, reason: &str) -> Result<Self, GamploError> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| GamploError::ModerationRule(format!("`{}`: {}", pattern, e)))?;
+        self.rules.push((regex, reason.to_string()));
+        Ok(self)
+    }
+    /// Checks `text` against the filter and records which path it took.
+    pub fn check(&self, text: &str) -> FilterVerdict {
+        let verdict = self.verdict(text);
+        let counter = match verdict {
+            FilterVerdict::Blocked(_) => &self.blocked_count,
+            FilterVerdict::Allowed => &self.allowed_count,
+            FilterVerdict::Pass => &self.passed_count,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        verdict
+    }
+    pub fn metrics(&self) -> FilterMetrics {
+        FilterMetrics {
+            blocked: self.blocked_count.load(Ordering::Relaxed),
+            allowed: self.allowed_count.load(Ordering::Relaxed),
+            passed: self.passed_count.load(Ordering::Relaxed),
+        }
+    }
+    pub fn reset_metrics(&self) {
+        self.blocked_count.store(0, Ordering::Relaxed);
+        self.allowed_count.store(0, Ordering::Relaxed);
+        self.passed_count.store(0, Ordering::Relaxed);
+    }
+
+    fn verdict(&self, text: &str) -> FilterVerdict {
+        let length = text.trim().chars().count();
+        if length < self.min_length {
+            return FilterVerdict::Blocked(format!("shorter than {} characters", self.min_length));
+        }
+        if let Some(max) = self.max_length.filter(|&max| length > max) {
+            return FilterVerdict::Blocked(format!("longer than {} characters", max));
+        }
+        let folded = fold(text);
+        if self.allowed.contains(&folded) {
+            return FilterVerdict::Allowed;
+        }
+        // Pad with spaces so blocked words only match whole words.
+        let padded = format!(" {} ", folded);
+        if self
+            .blocked
+            .iter()
+            .any(|word| padded.contains(&format!(" {} ", word)))
+        {
+            return FilterVerdict::Blocked("contains a blocked word".to_string());
+        }
+        match self.rules.iter().find(|(regex, _)| regex.is_match(text)) {
+            Some((_, reason)) => FilterVerdict::Blocked(reason.clone()),
+            None => FilterVerdict::Pass,
+        }
+    }
+}
+
+/// Folds text for matching: maps leetspeak and common Unicode confusables to ASCII letters,
+/// turns everything else that isn't a letter or digit into spaces, then [`normalize`]s it.
+pub fn fold(text: &str) -> String {
+    let mapped: String = text
+        .chars()
+        .map(|c| {
+            let c = fold_char(c);
+            if c.is_alphanumeric() { c } else { ' ' }
+        })
+        .collect();
+    normalize(&mapped)
+}
+
+fn fold_char(c: char) -> char {
+    match c {
+        // Fullwidth ASCII.
+        '\u{FF01}'..='\u{FF5E}' => fold_char(char::from_u32(c as u32 - 0xFEE0).unwrap_or(c)),
+        '0' => 'o',
+        '1' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        '8' => 'b',
+        '9' => 'g',
+        // Cyrillic and Greek letters that look like Latin ones.
+        'а' | 'А' | 'α' | 'Α' => 'a',
+        'в' | 'В' | 'β' | 'Β' => 'b',
+        'с' | 'С' | 'ϲ' => 'c',
+        'е' | 'Е' | 'ε' | 'Ε' => 'e',
+        'н' | 'Н' | 'Η' => 'h',
+        'і' | 'І' | 'ι' | 'Ι' => 'i',
+        'ј' | 'Ј' => 'j',
+        'к' | 'К' | 'κ' | 'Κ' => 'k',
+        'м' | 'М' | 'Μ' => 'm',
+        'η' | 'Ν' => 'n',
+        'о' | 'О' | 'ο' | 'Ο' => 'o',
+        'р' | 'Р' | 'ρ' | 'Ρ' => 'p',
+        'ѕ' | 'Ѕ' => 's',
+        'т' | 'Т' | 'τ' | 'Τ' => 't',
+        'υ' | 'μ' => 'u',
+        'х' | 'Х' | 'χ' | 'Χ' => 'x',
+        'у' | 'У' | 'γ' | 'Υ' => 'y',
+        'Ζ' => 'z',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_leetspeak_and_confusables() {
+        assert_eq!(fold("  B4D   w0rd!!"), "bad word");
+        assert_eq!(fold("bаd wοrd"), "bad word");
+        assert_eq!(fold("ＢＡＤ"), "bad");
+    }
+
+    #[test]
+    fn filters_and_counts() {
+        let filter = ModerationFilter::new()
+            .with_max_length(20)
+            .with_allowed("gg")
+            .with_blocked_words(["bad word", "darn"])
+            .with_rule(r"(?i)https?://", "links are not allowed")
+            .unwrap();
+        assert_eq!(filter.check("G G").result(), None);
+        assert_eq!(
+            filter.check(" GG ").result(),
+            Some(ModerationResult::Allowed)
+        );
+        assert!(
+            filter
+                .check("such a B4D w0rd")
+                .result()
+                .unwrap()
+                .is_blocked()
+        );
+        assert_eq!(filter.check("darned"), FilterVerdict::Pass);
+        assert_eq!(
+            filter.check("see https://x.io"),
+            FilterVerdict::Blocked("links are not allowed".to_string())
+        );
+        assert!(matches!(
+            filter.check("this is far too long to send"),
+            FilterVerdict::Blocked(_)
+        ));
+        assert_eq!(
+            filter.metrics(),
+            FilterMetrics {
+                blocked: 3,
+                allowed: 1,
+                passed: 2,
+            }
+        );
+        assert!(ModerationFilter::new().with_rule("(", "broken").is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use std::{fmt, ops::Deref};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{ModerationResult, backend::GamploBackend, error::GamploError};
+
+/// The key [`Moderated`] values are serialized under.
+pub const MODERATED_MARKER: &str = "$moderated";
+
+/// Text that has passed moderation.
+///
+/// [`Moderated::new`] runs the text through [`GamploBackend::moderate`]. Values serialize as
+/// `{"$moderated": <value>}`, and plain strings don't deserialize as `Moderated`, which catches
+/// data saved before a field was moderated.
+///
+/// Deserializing accepts any `{"$moderated": <value>}` without moderating it, so it's only
+/// trustworthy for data nobody else could have written. Saves on the player's device can be edited
+/// to add the marker; only the server-side `SaveIntegrity::Hmac` check (see
+/// [`crate::save::codec::SaveIntegrity`]) detects that. Data that is unchecked or only hashed,
+/// such as request bodies, should be moderated again with [`Moderated::recheck`], or with a
+/// [`crate::save::moderation::SaveModerator`], which moderates marked strings again.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Moderated<T = String>(T);
+impl<T: AsRef<str>> Moderated<T> {
+    /// Moderates `value` with `backend`, failing with [`GamploError::ModerationBlocked`] if it's blocked.
+    pub async fn new(
+        backend: &(impl GamploBackend + ?Sized),
+        value: T,
+    ) -> Result<Self, GamploError> {
+        match backend.moderate(value.as_ref()).await? {
+            ModerationResult::Allowed => Ok(Self(value)),
+            ModerationResult::Blocked { reason } => Err(GamploError::ModerationBlocked { reason }),
+        }
+    }
+    /// Moderates the value again, e.g. after deserializing it from data a player could have edited.
+    pub async fn recheck(
+        self,
+        backend: &(impl GamploBackend + ?Sized),
+    ) -> Result<Self, GamploError> {
+        Self::new(backend, self.0).await
+    }
+}
+impl<T> Moderated<T> {
+    pub fn get(&self) -> &T {
+        &self.0
+    }
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+impl<T> Deref for Moderated<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T: AsRef<str>> AsRef<str> for Moderated<T> {
+    fn as_ref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+impl<T: fmt::Display> fmt::Display for Moderated<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Marked<T> {
+    #[serde(rename = "$moderated")]
+    value: T,
+}
+
+impl<T: Serialize> Serialize for Moderated<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Marked { value: &self.0 }.serialize(serializer)
+    }
+}
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Moderated<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Marked::deserialize(deserializer).map(|marked| Self(marked.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        local::{LocalBackend, TestBackend},
+        save::binary::{SaveFormat, get_save_as, save_as},
+        util::block_on,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Profile {
+        name: Moderated,
+        level: u32,
+    }
+
+    #[test]
+    fn serializes_with_marker() {
+        let backend = LocalBackend::in_memory();
+        block_on(async {
+            let name = Moderated::new(&backend, "Jay".to_string()).await.unwrap();
+            assert_eq!(name.to_string(), "Jay");
+            assert_eq!(
+                serde_json::to_value(&name).unwrap(),
+                json!({ "$moderated": "Jay" })
+            );
+
+            let profile = Profile { name, level: 3 };
+            for (slot, format) in [(1, SaveFormat::Json), (2, SaveFormat::MessagePack)] {
+                save_as(&backend, Some(slot), &profile, format)
+                    .await
+                    .unwrap();
+                let loaded: Profile = get_save_as(&backend, slot).await.unwrap().unwrap();
+                assert_eq!(loaded, profile);
+            }
+        });
+    }
+
+    #[test]
+    fn rejects_unmarked_text() {
+        assert!(serde_json::from_value::<Moderated>(json!("Jay")).is_err());
+        assert!(serde_json::from_value::<Profile>(json!({ "name": "Jay", "level": 1 })).is_err());
+        assert!(serde_json::from_value::<Moderated>(json!({ "$moderated": "Jay" })).is_ok());
+    }
+
+    #[test]
+    fn rechecks_deserialized_text() {
+        let backend = TestBackend::new().with_moderation(|text| match text == "darn" {
+            true => ModerationResult::Blocked { reason: None },
+            false => ModerationResult::Allowed,
+        });
+        block_on(async {
+            let forged: Moderated =
+                serde_json::from_value(json!({ "$moderated": "darn" })).unwrap();
+            assert!(matches!(
+                forged.recheck(&backend).await,
+                Err(GamploError::ModerationBlocked { reason: None })
+            ));
+            let name: Moderated = serde_json::from_value(json!({ "$moderated": "Jay" })).unwrap();
+            assert_eq!(name.recheck(&backend).await.unwrap().get(), "Jay");
+        });
+    }
+}
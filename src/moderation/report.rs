@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{ModerationResult, error::GamploError};
+
+/// A part of a moderated text that was flagged.
+///
+/// Offsets count UTF-16 code units, like JavaScript string indices, with `end` exclusive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FlaggedSpan {
+    pub start: usize,
+    pub end: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+/// The full moderation response for a text, as returned by [`crate::Gamplo::moderate_detailed`].
+///
+/// Categories, scores and spans are empty when the response doesn't include them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModerationReport {
+    pub result: ModerationResult,
+    /// The categories the text was flagged for.
+    pub categories: Vec<String>,
+    /// Scores by category, usually from 0 to 1.
+    pub scores: BTreeMap<String, f64>,
+    pub spans: Vec<FlaggedSpan>,
+    /// The raw response, or `null` if the result didn't come from the server.
+    pub response: Value,
+}
+impl From<ModerationResult> for ModerationReport {
+    fn from(result: ModerationResult) -> Self {
+        Self {
+            result,
+            categories: Vec::new(),
+            scores: BTreeMap::new(),
+            spans: Vec::new(),
+            response: Value::Null,
+        }
+    }
+}
+impl ModerationReport {
+    /// Parses a moderation response.
+    ///
+    /// Responses without a boolean `blocked` are treated as allowed, unless `fail_closed` is set,
+    /// in which case they fail with [`GamploError::MissingField`].
+    pub fn parse(response: &str, fail_closed: bool) -> Result<Self, GamploError> {
+        let parsed: Value = serde_json::from_str(response)?;
+        let blocked = match parsed.get("blocked").and_then(Value::as_bool) {
+            Some(blocked) => blocked,
+            None if fail_closed => {
+                return Err(GamploError::MissingField {
+                    field: "blocked".to_string(),
+                    response: response.to_string(),
+                });
+            }
+            None => false,
+        };
+        let result = match blocked {
+            true => ModerationResult::Blocked {
+                reason: parsed
+                    .get("reason")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            },
+            false => ModerationResult::Allowed,
+        };
+        // Categories may be a list of names or a map of names to whether they were flagged.
+        let categories = match parsed.get("categories") {
+            Some(Value::Array(names)) => names
+                .iter()
+                .filter_map(|name| name.as_str().map(str::to_string))
+                .collect(),
+            Some(Value::Object(flags)) => flags
+                .iter()
+                .filter(|(_, flagged)| flagged.as_bool() == Some(true))
+                .map(|(name, _)| name.clone())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let scores = parsed
+            .get("scores")
+            .or_else(|| parsed.get("categoryScores"))
+            .and_then(Value::as_object)
+            .map(|scores| {
+                scores
+                    .iter()
+                    .filter_map(|(name, score)| Some((name.clone(), score.as_f64()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let spans = parsed
+            .get("spans")
+            .and_then(Value::as_array)
+            .map(|spans| {
+                spans
+                    .iter()
+                    .filter_map(|span| serde_json::from_value::<FlaggedSpan>(span.clone()).ok())
+                    .filter(|span| span.start < span.end)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            result,
+            categories,
+            scores,
+            spans,
+            response: parsed,
+        })
+    }
+    pub fn is_blocked(&self) -> bool {
+        self.result.is_blocked()
+    }
+    /// Returns `text` with every character inside a flagged span replaced by `mask`.
+    ///
+    /// `text` should be the text that was moderated. Spans past its end are clamped.
+    pub fn redact(&self, text: &str, mask: char) -> String {
+        let mut offset = 0;
+        text.chars()
+            .map(|c| {
+                let start = offset;
+                offset += c.len_utf16();
+                let flagged = self
+                    .spans
+                    .iter()
+                    .any(|span| start < span.end && offset > span.start);
+                if flagged { mask } else { c }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_full_response() {
+        let response = json!({
+            "blocked": true,
+            "reason": "profanity",
+            "categories": { "profanity": true, "spam": false },
+            "scores": { "profanity": 0.97, "spam": 0.1 },
+            "spans": [{ "start": 5, "end": 9, "category": "profanity" }, { "start": 3 }],
+        })
+        .to_string();
+        let report = ModerationReport::parse(&response, true).unwrap();
+        assert_eq!(
+            report.result,
+            ModerationResult::Blocked {
+                reason: Some("profanity".to_string())
+            }
+        );
+        assert_eq!(report.categories, ["profanity"]);
+        assert_eq!(report.scores["profanity"], 0.97);
+        assert_eq!(report.spans.len(), 1);
+        assert_eq!(report.response["reason"], json!("profanity"));
+        // The emoji is two UTF-16 code units.
+        assert_eq!(report.redact("🙂 a darn!", '*'), "🙂 a ****!");
+    }
+
+    #[test]
+    fn fails_closed_without_blocked() {
+        let report = ModerationReport::parse(r#"{"error":"busy"}"#, false).unwrap();
+        assert_eq!(report.result, ModerationResult::Allowed);
+        assert!(matches!(
+            ModerationReport::parse(r#"{"error":"busy"}"#, true),
+            Err(GamploError::MissingField { .. })
+        ));
+    }
+}
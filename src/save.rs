@@ -1,5 +1,23 @@
+pub mod autosave;
+pub mod backup;
+pub mod binary;
+pub mod bundle;
+pub mod codec;
+pub mod encryption;
+pub mod header;
+pub mod merge;
+pub mod moderation;
+pub mod patch;
+pub mod schema;
+pub mod transaction;
+
+use std::collections::BTreeMap;
+
+use futures_util::{StreamExt, stream};
 use serde_json::Value;
 
+use crate::{backend::GamploBackend, error::GamploError, save::header::SaveHeader};
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct SaveData {
@@ -8,6 +26,9 @@ pub struct SaveData {
     /// API: "sizeBytes"
     pub size_bytes: u64,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// The header the save was written with, see [`GamploBackend::save_with_header`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<SaveHeader>,
 }
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
@@ -42,3 +63,58 @@ pub struct SaveDeleteResponse {
     pub success: bool,
     pub deleted: bool,
 }
+
+/// Every slot's data, keyed by slot, with each slot's fetch error reported separately.
+pub type AllSaves = BTreeMap<u32, Result<SaveData, GamploError>>;
+
+/// Fetches the data of every slot listed by [`GamploBackend::get_saves`], at most `concurrency` at a time.
+///
+/// Only failing to list the slots fails the whole call. Slots deleted while fetching are left out.
+pub(crate) async fn get_all_saves<B: GamploBackend + ?Sized>(
+    backend: &B,
+    concurrency: usize,
+) -> Result<AllSaves, GamploError> {
+    let saves = backend.get_saves().await?;
+    Ok(get_saves_concurrently(backend, saves.saves.iter().map(|s| s.slot), concurrency).await)
+}
+
+/// Fetches the data of `slots`, at most `concurrency` at a time. Empty slots are left out.
+pub(crate) async fn get_saves_concurrently<B: GamploBackend + ?Sized>(
+    backend: &B,
+    slots: impl IntoIterator<Item = u32>,
+    concurrency: usize,
+) -> AllSaves {
+    let fetched: Vec<_> = stream::iter(slots)
+        .map(|slot| async move { (slot, backend.get_save(slot).await) })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    fetched
+        .into_iter()
+        .filter_map(|(slot, save)| save.transpose().map(|save| (slot, save)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{local::TestBackend, util::block_on};
+
+    #[test]
+    fn gets_all_saves_with_per_slot_errors() {
+        let backend = TestBackend::new();
+        backend.fail_reads(2);
+        block_on(async {
+            for slot in [1, 2, 5] {
+                backend.save(Some(slot), json!(slot)).await.unwrap();
+            }
+            let saves = backend.get_all_saves(2).await.unwrap();
+            assert_eq!(saves.keys().copied().collect::<Vec<_>>(), [1, 2, 5]);
+            assert_eq!(saves[&1].as_ref().unwrap().data, json!(1));
+            assert_eq!(saves[&5].as_ref().unwrap().data, json!(5));
+            assert!(saves[&2].is_err());
+        });
+    }
+}
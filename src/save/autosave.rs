@@ -0,0 +1,386 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde_json::Value;
+
+use crate::{
+    backend::GamploBackend,
+    error::GamploError,
+    save::{SaveWriteResponse, header::SaveHeader},
+};
+
+/// Timing rules for an [`Autosave`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AutosaveConfig {
+    /// How long a slot must go without changes before it is written.
+    pub debounce: Duration,
+    /// The minimum time between two writes of the same slot.
+    pub min_interval: Duration,
+    /// The longest a change may wait while the slot keeps changing, so constant changes still get saved.
+    pub max_delay: Duration,
+}
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_secs(2),
+            min_interval: Duration::from_secs(10),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Pending {
+    data: Value,
+    hash: u64,
+    dirty_since: DateTime<Utc>,
+    changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Written {
+    hash: u64,
+    at: Option<DateTime<Utc>>,
+}
+
+/// The outcome of writing each slot, keyed by slot. Slots that failed stay pending, so a later
+/// write retries them.
+pub type AutosaveWrites = BTreeMap<u32, Result<SaveWriteResponse, GamploError>>;
+
+/// Debounces and coalesces save writes per slot.
+///
+/// Call [`Autosave::mark_dirty`] with the latest state whenever it changes, and
+/// [`Autosave::tick`] regularly (e.g. once per second or at [`Autosave::next_due`]).
+/// Only the latest state of each slot is written, and writes whose content is unchanged since the last write are skipped.
+/// Call [`Autosave::flush`] before exiting, or on the web use [`flush_on_exit`] to flush when the page is hidden.
+#[derive(Debug, Clone, Default)]
+pub struct Autosave {
+    config: AutosaveConfig,
+    pending: BTreeMap<u32, Pending>,
+    written: HashMap<u32, Written>,
+    headers: HashMap<u32, SaveHeader>,
+}
+impl Autosave {
+    pub fn new(config: AutosaveConfig) -> Self {
+        Self {
+            config,
+            pending: BTreeMap::new(),
+            written: HashMap::new(),
+            headers: HashMap::new(),
+        }
+    }
+    /// Sets the header `slot` is written with from now on. Slots without one are written without
+    /// a header, which removes any header they had.
+    pub fn set_header(&mut self, slot: u32, header: Option<SaveHeader>) {
+        match header {
+            Some(header) => self.headers.insert(slot, header),
+            None => self.headers.remove(&slot),
+        };
+    }
+    /// Records `data` as the content currently stored in `slot`, e.g. right after loading it,
+    /// so marking the same content dirty does not upload it again.
+    pub fn set_baseline(&mut self, slot: u32, data: &Value) {
+        self.written.insert(
+            slot,
+            Written {
+                hash: content_hash(data),
+                at: None,
+            },
+        );
+    }
+    /// Records the latest state of `slot`, replacing any state still waiting to be written.
+    pub fn mark_dirty(&mut self, slot: u32, data: Value) {
+        self.mark_dirty_at(slot, data, Utc::now());
+    }
+    /// Like [`Autosave::mark_dirty`], but with an explicit current time.
+    pub fn mark_dirty_at(&mut self, slot: u32, data: Value, now: DateTime<Utc>) {
+        let hash = content_hash(&data);
+        if self.written.get(&slot).is_some_and(|w| w.hash == hash) {
+            // Back to what is already stored; nothing needs to be written.
+            self.pending.remove(&slot);
+            return;
+        }
+        let dirty_since = self.pending.get(&slot).map_or(now, |p| p.dirty_since);
+        self.pending.insert(
+            slot,
+            Pending {
+                data,
+                hash,
+                dirty_since,
+                changed_at: now,
+            },
+        );
+    }
+    /// Whether any slot has changes that have not been written yet.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+    /// The slots whose changes should be written at `now`.
+    pub fn due_slots(&self, now: DateTime<Utc>) -> Vec<u32> {
+        self.pending
+            .iter()
+            .filter(|(slot, pending)| self.due_at(**slot, pending) <= now)
+            .map(|(slot, _)| *slot)
+            .collect()
+    }
+    /// The earliest time at which a pending slot becomes due, if any slot is pending.
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.pending
+            .iter()
+            .map(|(slot, pending)| self.due_at(*slot, pending))
+            .min()
+    }
+    /// Writes every slot that is due now.
+    pub async fn tick(&mut self, backend: &impl GamploBackend) -> AutosaveWrites {
+        let due = self.due_slots(Utc::now());
+        self.write(backend, due).await
+    }
+    /// Writes every pending slot immediately, ignoring the debounce and minimum interval.
+    pub async fn flush(&mut self, backend: &impl GamploBackend) -> AutosaveWrites {
+        let slots = self.pending.keys().copied().collect();
+        self.write(backend, slots).await
+    }
+    /// Sends every pending slot with `fetch(..., { keepalive: true })`, so the requests survive the page being unloaded.
+    ///
+    /// The responses are not awaited, so sent slots are assumed written. Browsers limit keepalive
+    /// bodies to [`crate::KEEPALIVE_BODY_LIMIT`] in total, so slots that no longer fit are not sent,
+    /// fail with [`GamploError::KeepaliveTooLarge`] and stay pending; flush large saves with
+    /// [`Autosave::flush`] earlier. Slots that fail to send stay pending as well.
+    #[cfg(all(feature = "client", target_arch = "wasm32"))]
+    pub fn flush_keepalive(
+        &mut self,
+        gamplo: &crate::Gamplo,
+    ) -> BTreeMap<u32, Result<(), GamploError>> {
+        let mut budget = crate::KEEPALIVE_BODY_LIMIT;
+        let mut results = BTreeMap::new();
+        let slots: Vec<u32> = self.pending.keys().copied().collect();
+        for slot in slots {
+            let pending = &self.pending[&slot];
+            let result = gamplo.send_keepalive(
+                Some(slot),
+                self.headers.get(&slot),
+                pending.data.clone(),
+                &mut budget,
+            );
+            if result.is_ok() {
+                self.mark_written(slot);
+            }
+            results.insert(slot, result);
+        }
+        results
+    }
+
+    async fn write(&mut self, backend: &impl GamploBackend, slots: Vec<u32>) -> AutosaveWrites {
+        let mut results = BTreeMap::new();
+        for slot in slots {
+            let Some(pending) = self.pending.get(&slot) else {
+                continue;
+            };
+            // On failure the slot stays pending so the next tick retries it.
+            let result = backend
+                .save_with_header(Some(slot), self.headers.get(&slot), pending.data.clone())
+                .await;
+            if result.is_ok() {
+                self.mark_written(slot);
+            }
+            results.insert(slot, result);
+        }
+        results
+    }
+    fn mark_written(&mut self, slot: u32) {
+        if let Some(pending) = self.pending.remove(&slot) {
+            let written = Written {
+                hash: pending.hash,
+                at: Some(Utc::now()),
+            };
+            self.written.insert(slot, written);
+        }
+    }
+    fn due_at(&self, slot: u32, pending: &Pending) -> DateTime<Utc> {
+        // Durations too long to represent, e.g. `Duration::MAX` for "no limit", never come due.
+        let after = |at: DateTime<Utc>, d: Duration| {
+            TimeDelta::from_std(d)
+                .ok()
+                .and_then(|d| at.checked_add_signed(d))
+                .unwrap_or(DateTime::<Utc>::MAX_UTC)
+        };
+        let settled = after(pending.changed_at, self.config.debounce)
+            .min(after(pending.dirty_since, self.config.max_delay));
+        match self.written.get(&slot).and_then(|w| w.at) {
+            Some(at) => settled.max(after(at, self.config.min_interval)),
+            None => settled,
+        }
+    }
+}
+
+fn content_hash(data: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Keeps the listeners installed by [`flush_on_exit`] alive. Dropping it removes them.
+#[cfg(all(feature = "client", target_arch = "wasm32"))]
+pub struct ExitFlush {
+    listener: web_sys::wasm_bindgen::closure::Closure<dyn FnMut()>,
+}
+#[cfg(all(feature = "client", target_arch = "wasm32"))]
+impl Drop for ExitFlush {
+    fn drop(&mut self) {
+        use web_sys::wasm_bindgen::JsCast;
+        let callback = self.listener.as_ref().unchecked_ref();
+        if let Some(window) = web_sys::window() {
+            let _ = window.remove_event_listener_with_callback("pagehide", callback);
+            if let Some(document) = window.document() {
+                let _ = document.remove_event_listener_with_callback("visibilitychange", callback);
+            }
+        }
+    }
+}
+
+/// Flushes `autosave` with [`Autosave::flush_keepalive`] whenever the page becomes hidden
+/// (`visibilitychange`) or is unloaded (`pagehide`).
+///
+/// The listeners stay installed for as long as the returned [`ExitFlush`] is kept alive.
+#[cfg(all(feature = "client", target_arch = "wasm32"))]
+pub fn flush_on_exit(
+    autosave: std::rc::Rc<std::cell::RefCell<Autosave>>,
+    gamplo: crate::Gamplo,
+) -> Result<ExitFlush, GamploError> {
+    use web_sys::wasm_bindgen::{JsCast, closure::Closure};
+
+    let window =
+        web_sys::window().ok_or_else(|| GamploError::Wasm("Failed to get window object".into()))?;
+    let document = window
+        .document()
+        .ok_or_else(|| GamploError::Wasm("Failed to get document object".into()))?;
+
+    let hidden_document = document.clone();
+    let listener = Closure::<dyn FnMut()>::new(move || {
+        if hidden_document.visibility_state() != web_sys::VisibilityState::Hidden {
+            return;
+        }
+        // Nothing useful can be done with an error while the page is going away.
+        if let Ok(mut autosave) = autosave.try_borrow_mut() {
+            let _ = autosave.flush_keepalive(&gamplo);
+        }
+    });
+    let callback = listener.as_ref().unchecked_ref();
+    let to_error = |e| GamploError::Wasm(format!("Failed to add event listener: {:?}", e));
+    document
+        .add_event_listener_with_callback("visibilitychange", callback)
+        .map_err(to_error)?;
+    window
+        .add_event_listener_with_callback("pagehide", callback)
+        .map_err(to_error)?;
+    Ok(ExitFlush { listener })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        local::{LocalBackend, TestBackend},
+        util::block_on,
+    };
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn debounces_and_limits_writes() {
+        let mut autosave = Autosave::new(AutosaveConfig {
+            debounce: Duration::from_secs(2),
+            min_interval: Duration::from_secs(10),
+            max_delay: Duration::from_secs(5),
+        });
+        autosave.mark_dirty_at(1, json!(1), at(0));
+        autosave.mark_dirty_at(1, json!(2), at(1));
+        assert!(autosave.due_slots(at(2)).is_empty());
+        assert_eq!(autosave.due_slots(at(3)), [1]);
+
+        // Changes every second never settle, but are forced out after `max_delay`.
+        for t in 2..5 {
+            autosave.mark_dirty_at(1, json!(t), at(t));
+        }
+        assert_eq!(autosave.due_slots(at(5)), [1]);
+        assert_eq!(autosave.next_due(), Some(at(5)));
+    }
+
+    #[test]
+    fn unlimited_durations_do_not_overflow() {
+        let mut autosave = Autosave::new(AutosaveConfig {
+            debounce: Duration::from_secs(2),
+            min_interval: Duration::MAX,
+            max_delay: Duration::MAX,
+        });
+        autosave.mark_dirty_at(1, json!(1), at(0));
+        assert!(autosave.due_slots(at(1)).is_empty());
+        assert_eq!(autosave.due_slots(at(2)), [1]);
+
+        autosave.written.insert(
+            1,
+            Written {
+                hash: 0,
+                at: Some(at(2)),
+            },
+        );
+        assert_eq!(autosave.next_due(), Some(DateTime::<Utc>::MAX_UTC));
+        assert!(autosave.due_slots(at(1_000_000)).is_empty());
+    }
+
+    #[test]
+    fn skips_unchanged_content() {
+        let backend = LocalBackend::in_memory();
+        let mut autosave = Autosave::default();
+        autosave.set_baseline(1, &json!({"level": 1}));
+        autosave.mark_dirty(1, json!({"level": 1}));
+        assert!(!autosave.has_pending());
+
+        autosave.mark_dirty(1, json!({"level": 2}));
+        autosave.mark_dirty(2, json!({"level": 3}));
+        autosave.set_header(1, Some(SaveHeader::named("Hero")));
+        let written = block_on(autosave.flush(&backend));
+        assert_eq!(written.len(), 2);
+        assert!(written.values().all(Result::is_ok));
+        assert!(!autosave.has_pending());
+        let save = block_on(backend.get_save(1)).unwrap().unwrap();
+        assert_eq!(save.data, json!({"level": 2}));
+        assert_eq!(save.header, Some(SaveHeader::named("Hero")));
+
+        autosave.mark_dirty(1, json!({"level": 2}));
+        assert!(!autosave.has_pending());
+    }
+
+    #[test]
+    fn keeps_writing_after_a_failed_slot() {
+        let backend = TestBackend::new();
+        backend.fail_writes(1);
+        let mut autosave = Autosave::default();
+        autosave.mark_dirty(1, json!(1));
+        autosave.mark_dirty(2, json!(2));
+
+        let written = block_on(autosave.flush(&backend));
+        assert!(written[&1].is_err());
+        assert!(written[&2].is_ok());
+        assert_eq!(
+            block_on(backend.get_save(2)).unwrap().unwrap().data,
+            json!(2)
+        );
+
+        // The failed slot stays pending and is retried.
+        backend.heal(1);
+        assert!(autosave.has_pending());
+        let written = block_on(autosave.flush(&backend));
+        assert_eq!(written.keys().copied().collect::<Vec<_>>(), [1]);
+        assert!(written[&1].is_ok());
+        assert!(!autosave.has_pending());
+    }
+}
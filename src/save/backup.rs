@@ -0,0 +1,241 @@
+use std::{cmp::Reverse, collections::HashSet};
+
+use serde_json::Value;
+
+use crate::{
+    backend::GamploBackend,
+    error::GamploError,
+    save::{SaveData, SaveMetadata, SaveWriteResponse, Saves},
+};
+
+/// Keeps previous versions of a primary save slot in a ring of backup slots.
+///
+/// Before [`BackupPolicy::save`] overwrites the primary slot, its current contents are copied into
+/// an empty backup slot, or into the backup slot that was updated least recently.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BackupPolicy {
+    primary: u32,
+    backups: Vec<u32>,
+}
+impl BackupPolicy {
+    /// Creates a policy that backs `primary` up into `backups`.
+    pub fn new(primary: u32, backups: impl IntoIterator<Item = u32>) -> Self {
+        Self {
+            primary,
+            backups: backups.into_iter().collect(),
+        }
+    }
+    pub fn primary(&self) -> u32 {
+        self.primary
+    }
+    pub fn backups(&self) -> &[u32] {
+        &self.backups
+    }
+    /// Checks that the slots are distinct and numbered from 1 to [`Saves::max_slots`].
+    pub fn validate(&self, saves: &Saves) -> Result<(), GamploError> {
+        let mut seen = HashSet::from([self.primary]);
+        if let Some(slot) = self.backups.iter().find(|slot| !seen.insert(**slot)) {
+            return Err(GamploError::SlotConfig(format!(
+                "backup slot {} is used more than once or is the primary slot",
+                slot
+            )));
+        }
+        if let Some(slot) = seen
+            .iter()
+            .find(|slot| !(1..=saves.max_slots).contains(*slot))
+        {
+            return Err(GamploError::InvalidSlot {
+                slot: *slot,
+                max_slots: saves.max_slots,
+            });
+        }
+        Ok(())
+    }
+    /// Writes `data` to the primary slot after rotating its previous contents into a backup slot.
+    ///
+    /// If the primary slot's contents can't be decoded (e.g. they are corrupted or fail their
+    /// integrity check), they are not worth backing up and are overwritten without rotating.
+    pub async fn save(
+        &self,
+        backend: &impl GamploBackend,
+        data: Value,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        let saves = backend.get_saves().await?;
+        self.validate(&saves)?;
+        if let Some(target) = self.rotation_target(&saves)
+            && let Some(current) = readable(backend.get_save(self.primary).await)?
+        {
+            backend
+                .save_with_header(Some(target), current.header.as_ref(), current.data)
+                .await?;
+        }
+        backend.save(Some(self.primary), data).await
+    }
+    /// Lists the existing backups, newest first.
+    pub async fn list(
+        &self,
+        backend: &impl GamploBackend,
+    ) -> Result<Vec<SaveMetadata>, GamploError> {
+        let mut backups: Vec<SaveMetadata> = backend
+            .get_saves()
+            .await?
+            .saves
+            .into_iter()
+            .filter(|s| self.backups.contains(&s.slot))
+            .collect();
+        backups.sort_by_key(|b| Reverse(b.updated_at));
+        Ok(backups)
+    }
+    /// Reads a backup slot without restoring it, e.g. to preview it.
+    pub async fn get(
+        &self,
+        backend: &impl GamploBackend,
+        backup: u32,
+    ) -> Result<Option<SaveData>, GamploError> {
+        self.check_backup(backup)?;
+        backend.get_save(backup).await
+    }
+    /// Copies a backup slot into the primary slot.
+    ///
+    /// The primary slot's current contents are not backed up, since restoring usually means they are broken.
+    pub async fn restore(
+        &self,
+        backend: &impl GamploBackend,
+        backup: u32,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        let data = self
+            .get(backend, backup)
+            .await?
+            .ok_or(GamploError::EmptySlot { slot: backup })?;
+        backend
+            .save_with_header(Some(self.primary), data.header.as_ref(), data.data)
+            .await
+    }
+    /// Deletes all but the `keep` newest backups and returns the deleted slots.
+    pub async fn prune(
+        &self,
+        backend: &impl GamploBackend,
+        keep: usize,
+    ) -> Result<Vec<u32>, GamploError> {
+        let mut deleted = Vec::new();
+        for backup in self.list(backend).await?.into_iter().skip(keep) {
+            backend.delete_save(backup.slot).await?;
+            deleted.push(backup.slot);
+        }
+        Ok(deleted)
+    }
+
+    /// The first empty backup slot, or else the least recently updated one.
+    fn rotation_target(&self, saves: &Saves) -> Option<u32> {
+        let updated_at = |slot: u32| {
+            saves
+                .saves
+                .iter()
+                .find(|s| s.slot == slot)
+                .map(|s| s.updated_at)
+        };
+        self.backups
+            .iter()
+            .copied()
+            .min_by_key(|slot| (updated_at(*slot).is_some(), updated_at(*slot)))
+    }
+    fn check_backup(&self, backup: u32) -> Result<(), GamploError> {
+        if !self.backups.contains(&backup) {
+            return Err(GamploError::SlotConfig(format!(
+                "slot {} is not a backup slot of slot {}",
+                backup, self.primary
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Treats a save that could be read but not decoded as missing.
+fn readable(save: Result<Option<SaveData>, GamploError>) -> Result<Option<SaveData>, GamploError> {
+    match save {
+        Err(
+            GamploError::SaveCorrupted { .. }
+            | GamploError::SaveTampered { .. }
+            | GamploError::SaveDecryption { .. }
+            | GamploError::SaveValidation { .. }
+            | GamploError::Deserialization { .. },
+        ) => Ok(None),
+        save => save,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        local::{LocalBackend, TestBackend},
+        util::block_on,
+    };
+
+    #[test]
+    fn rotates_restores_and_prunes() {
+        let backend = LocalBackend::in_memory().with_limits(3, 1024);
+        let policy = BackupPolicy::new(1, [2, 3]);
+        block_on(async {
+            for version in 1..=4 {
+                policy.save(&backend, json!(version)).await.unwrap();
+            }
+            let backend = &backend;
+            let data = |slot| async move { backend.get_save(slot).await.unwrap().unwrap().data };
+            assert_eq!(data(1).await, json!(4));
+            // Version 1 went to slot 2, version 2 to slot 3, then version 3 replaced the oldest (slot 2).
+            assert_eq!(data(2).await, json!(3));
+            assert_eq!(data(3).await, json!(2));
+
+            let backups = policy.list(backend).await.unwrap();
+            assert_eq!(backups.iter().map(|b| b.slot).collect::<Vec<_>>(), [2, 3]);
+
+            policy.restore(backend, 3).await.unwrap();
+            assert_eq!(data(1).await, json!(2));
+            assert!(policy.restore(backend, 1).await.is_err());
+
+            assert_eq!(policy.prune(backend, 1).await.unwrap(), [3]);
+            assert!(backend.get_save(3).await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn overwrites_undecodable_primary() {
+        let backend = TestBackend::new();
+        let policy = BackupPolicy::new(1, [2]);
+        block_on(async {
+            policy.save(&backend, json!(1)).await.unwrap();
+            backend.corrupt(1);
+            policy.save(&backend, json!(2)).await.unwrap();
+            backend.heal(1);
+            assert_eq!(backend.get_save(1).await.unwrap().unwrap().data, json!(2));
+            assert!(backend.get_save(2).await.unwrap().is_none());
+
+            // Other read failures still fail the save, so a backup isn't lost by accident.
+            backend.fail_reads(1);
+            assert!(policy.save(&backend, json!(3)).await.is_err());
+        });
+    }
+
+    #[test]
+    fn validates_slots() {
+        let saves = Saves {
+            saves: Vec::new(),
+            max_slots: 2,
+            max_size_bytes: 0,
+        };
+        assert!(BackupPolicy::new(1, [2]).validate(&saves).is_ok());
+        assert!(matches!(
+            BackupPolicy::new(1, [2, 3]).validate(&saves),
+            Err(GamploError::InvalidSlot { slot: 3, .. })
+        ));
+        assert!(matches!(
+            BackupPolicy::new(1, [1]).validate(&saves),
+            Err(GamploError::SlotConfig(_))
+        ));
+        assert!(BackupPolicy::new(1, [5]).validate(&saves).is_err());
+        assert!(BackupPolicy::new(0, [1]).validate(&saves).is_err());
+    }
+}
@@ -0,0 +1,323 @@
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{backend::GamploBackend, error::GamploError, save::SaveData};
+
+/// The bundle format version written by [`export_saves`].
+pub const BUNDLE_VERSION: u32 = 1;
+/// The first bytes of a bundle in the binary format.
+const BINARY_MAGIC: &[u8; 4] = b"GPSB";
+
+/// Every save slot of a player, as exported by [`export_saves`].
+///
+/// Holds the decoded payloads, as returned by [`GamploBackend::get_save`], so a bundle exported
+/// from a [`crate::Gamplo`] with save encryption configured is not encrypted itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveBundle {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// The number of slots the exporting backend had.
+    pub max_slots: u32,
+    /// Every non-empty slot, ordered by slot.
+    pub saves: Vec<SaveData>,
+}
+
+/// A bundle as written to JSON, carrying a checksum of its contents.
+#[derive(Serialize, Deserialize)]
+struct ChecksummedBundle {
+    #[serde(flatten)]
+    bundle: SaveBundle,
+    /// Base64 SHA-256 of the bundle's JSON without this field.
+    checksum: String,
+}
+
+impl SaveBundle {
+    /// Writes the bundle as pretty-printed JSON, readable by [`SaveBundle::from_json`].
+    pub fn to_json(&self) -> Result<String, GamploError> {
+        Ok(serde_json::to_string_pretty(&ChecksummedBundle {
+            bundle: self.clone(),
+            checksum: BASE64.encode(self.checksum()?),
+        })?)
+    }
+    /// Reads a bundle written by [`SaveBundle::to_json`], verifying its checksum and version.
+    pub fn from_json(json: &str) -> Result<Self, GamploError> {
+        let stored: ChecksummedBundle =
+            serde_json::from_str(json).map_err(|e| GamploError::Deserialization {
+                type_name: std::any::type_name::<SaveBundle>().to_string(),
+                data: json.to_string(),
+                source: e,
+            })?;
+        let checksum = BASE64
+            .decode(&stored.checksum)
+            .map_err(|_| invalid("checksum is not base64"))?;
+        stored.bundle.verify(&checksum)
+    }
+    /// Writes the bundle in a compact binary format (a header, then MessagePack), readable by [`SaveBundle::from_bytes`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GamploError> {
+        let body = rmp_serde::to_vec_named(self)
+            .map_err(|e| GamploError::SaveEncoding(format!("save bundle: {}", e)))?;
+        let mut bytes = Vec::with_capacity(BINARY_MAGIC.len() + 32 + body.len());
+        bytes.extend_from_slice(BINARY_MAGIC);
+        bytes.extend_from_slice(&self.checksum()?);
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+    /// Reads a bundle written by [`SaveBundle::to_bytes`], verifying its checksum and version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GamploError> {
+        let rest = bytes
+            .strip_prefix(BINARY_MAGIC)
+            .ok_or_else(|| invalid("not a save bundle"))?;
+        if rest.len() < 32 {
+            return Err(invalid("bundle is truncated"));
+        }
+        let (checksum, body) = rest.split_at(32);
+        let bundle: SaveBundle =
+            rmp_serde::from_slice(body).map_err(|e| invalid(&e.to_string()))?;
+        bundle.verify(checksum)
+    }
+
+    /// SHA-256 of the bundle's JSON. Both formats use it, so converting between them keeps the checksum.
+    fn checksum(&self) -> Result<[u8; 32], GamploError> {
+        Ok(Sha256::digest(serde_json::to_vec(self)?).into())
+    }
+    fn verify(self, checksum: &[u8]) -> Result<Self, GamploError> {
+        if self.version > BUNDLE_VERSION {
+            return Err(invalid(&format!(
+                "bundle version {} is newer than this SDK supports",
+                self.version
+            )));
+        }
+        if self.checksum()? != checksum {
+            return Err(invalid("checksum does not match"));
+        }
+        Ok(self)
+    }
+}
+
+fn invalid(reason: &str) -> GamploError {
+    GamploError::InvalidBundle(reason.to_string())
+}
+
+/// What to do with a bundled save whose slot already holds a save.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ImportStrategy {
+    /// Replace the existing save with the bundled one.
+    #[default]
+    Overwrite,
+    /// Keep the existing save.
+    Skip,
+    /// Merge the bundled save into the existing one. JSON objects are merged key by key,
+    /// recursively, with the bundled values winning; any other data is replaced.
+    /// The bundled header is kept if the bundled save has one.
+    Merge,
+}
+
+/// What [`import_saves`] did, or would do, with one bundled save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlotImport {
+    /// The slot was empty, so the bundled save was written.
+    Created { slot: u32 },
+    /// The existing save was replaced.
+    Overwritten { slot: u32 },
+    /// The existing save was kept.
+    Skipped { slot: u32 },
+    /// The bundled save was merged into the existing one.
+    Merged { slot: u32 },
+}
+
+/// The outcome of [`import_saves`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub slots: Vec<SlotImport>,
+    /// Whether this is only a preview and nothing was written.
+    pub dry_run: bool,
+}
+
+/// Fetches every save slot of `backend` into a [`SaveBundle`].
+pub async fn export_saves(backend: &impl GamploBackend) -> Result<SaveBundle, GamploError> {
+    let saves = backend.get_saves().await?;
+    let mut bundle = SaveBundle {
+        version: BUNDLE_VERSION,
+        exported_at: Utc::now(),
+        max_slots: saves.max_slots,
+        saves: Vec::new(),
+    };
+    for metadata in saves.saves {
+        if let Some(save) = backend.get_save(metadata.slot).await? {
+            bundle.saves.push(save);
+        }
+    }
+    bundle.saves.sort_by_key(|s| s.slot);
+    Ok(bundle)
+}
+
+/// Writes the saves of `bundle` into `backend`, resolving occupied slots with `strategy`.
+///
+/// With `dry_run`, nothing is written and the report describes what the import would do.
+/// Bundled slots that do not exist on `backend` abort the import before anything is written.
+pub async fn import_saves(
+    backend: &impl GamploBackend,
+    bundle: &SaveBundle,
+    strategy: ImportStrategy,
+    dry_run: bool,
+) -> Result<ImportReport, GamploError> {
+    let saves = backend.get_saves().await?;
+    if let Some(save) = bundle
+        .saves
+        .iter()
+        .find(|s| s.slot == 0 || s.slot > saves.max_slots)
+    {
+        return Err(GamploError::InvalidSlot {
+            slot: save.slot,
+            max_slots: saves.max_slots,
+        });
+    }
+
+    let mut report = ImportReport {
+        slots: Vec::new(),
+        dry_run,
+    };
+    for save in &bundle.saves {
+        let slot = save.slot;
+        let occupied = saves.saves.iter().any(|s| s.slot == slot);
+        let bundled = || Some((save.header.clone(), save.data.clone()));
+        let (action, write) = match (occupied, strategy) {
+            (false, _) => (SlotImport::Created { slot }, bundled()),
+            (true, ImportStrategy::Overwrite) => (SlotImport::Overwritten { slot }, bundled()),
+            (true, ImportStrategy::Skip) => (SlotImport::Skipped { slot }, None),
+            (true, ImportStrategy::Merge) => {
+                let write = match backend.get_save(slot).await? {
+                    Some(existing) => Some((
+                        save.header.clone().or(existing.header),
+                        merge(existing.data, &save.data),
+                    )),
+                    None => bundled(),
+                };
+                (SlotImport::Merged { slot }, write)
+            }
+        };
+        if let Some((header, data)) = write
+            && !dry_run
+        {
+            backend
+                .save_with_header(Some(slot), header.as_ref(), data)
+                .await?;
+        }
+        report.slots.push(action);
+    }
+    Ok(report)
+}
+
+fn merge(existing: Value, bundled: &Value) -> Value {
+    match (existing, bundled) {
+        (Value::Object(mut existing), Value::Object(bundled)) => {
+            for (key, value) in bundled {
+                let merged = match existing.remove(key) {
+                    Some(current) => merge(current, value),
+                    None => value.clone(),
+                };
+                existing.insert(key.clone(), merged);
+            }
+            Value::Object(existing)
+        }
+        (_, bundled) => bundled.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{local::LocalBackend, util::block_on};
+
+    #[test]
+    fn round_trips_both_formats() {
+        let backend = LocalBackend::in_memory();
+        block_on(async {
+            backend.save(Some(2), json!({"level": 3})).await.unwrap();
+            backend.save(Some(1), json!("settings")).await.unwrap();
+            let bundle = export_saves(&backend).await.unwrap();
+            assert_eq!(
+                bundle.saves.iter().map(|s| s.slot).collect::<Vec<_>>(),
+                [1, 2]
+            );
+
+            assert_eq!(
+                SaveBundle::from_json(&bundle.to_json().unwrap()).unwrap(),
+                bundle
+            );
+            let bytes = bundle.to_bytes().unwrap();
+            assert_eq!(SaveBundle::from_bytes(&bytes).unwrap(), bundle);
+
+            let tampered = bundle.to_json().unwrap().replace("settings", "s3ttings");
+            assert!(matches!(
+                SaveBundle::from_json(&tampered),
+                Err(GamploError::InvalidBundle(reason)) if reason == "checksum does not match"
+            ));
+            assert!(matches!(
+                SaveBundle::from_bytes(&bytes[..bytes.len() - 1]),
+                Err(GamploError::InvalidBundle(_))
+            ));
+        });
+    }
+
+    #[test]
+    fn imports_with_strategies() {
+        let source = LocalBackend::in_memory();
+        let target = LocalBackend::in_memory();
+        block_on(async {
+            source
+                .save(Some(1), json!({"gold": 5, "options": {"music": false}}))
+                .await
+                .unwrap();
+            source.save(Some(2), json!("bundled")).await.unwrap();
+            target
+                .save(Some(1), json!({"gems": 1, "options": {"sfx": true}}))
+                .await
+                .unwrap();
+            let bundle = export_saves(&source).await.unwrap();
+
+            let preview = import_saves(&target, &bundle, ImportStrategy::Merge, true)
+                .await
+                .unwrap();
+            assert!(preview.dry_run);
+            assert_eq!(
+                preview.slots,
+                [
+                    SlotImport::Merged { slot: 1 },
+                    SlotImport::Created { slot: 2 }
+                ]
+            );
+            assert!(target.get_save(2).await.unwrap().is_none());
+
+            import_saves(&target, &bundle, ImportStrategy::Merge, false)
+                .await
+                .unwrap();
+            assert_eq!(
+                target.get_save(1).await.unwrap().unwrap().data,
+                json!({"gems": 1, "gold": 5, "options": {"music": false, "sfx": true}})
+            );
+
+            target.save(Some(2), json!("local")).await.unwrap();
+            let report = import_saves(&target, &bundle, ImportStrategy::Skip, false)
+                .await
+                .unwrap();
+            assert_eq!(report.slots[1], SlotImport::Skipped { slot: 2 });
+            assert_eq!(
+                target.get_save(2).await.unwrap().unwrap().data,
+                json!("local")
+            );
+
+            let small = LocalBackend::in_memory().with_limits(1, 1024);
+            assert!(matches!(
+                import_saves(&small, &bundle, ImportStrategy::Overwrite, false).await,
+                Err(GamploError::InvalidSlot { max_slots: 1, .. })
+            ));
+        });
+    }
+}
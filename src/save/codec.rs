@@ -0,0 +1,538 @@
+use std::collections::BTreeMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::GamploError,
+    save::{
+        binary::SaveFormat,
+        encryption::{EncryptionTag, SaveEncryption},
+        header::SaveHeader,
+        schema::SaveSchema,
+    },
+};
+
+/// The key marking save data as a [`SaveEnvelope`]. Its value is the envelope format version.
+pub(crate) const ENVELOPE_KEY: &str = "$gamplo";
+pub(crate) const ENVELOPE_VERSION: u32 = 1;
+
+/// What is stored in [`crate::save::SaveData::data`] when a [`SaveCodec`] is configured:
+/// the game's payload plus whatever the codec needs to decode and check it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SaveEnvelope {
+    #[serde(rename = "$gamplo")]
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<SaveHeader>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<IntegrityTag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<EncryptionTag>,
+    /// The binary format of the payload, see [`crate::save::binary`]. Only set on the envelopes
+    /// binary saves are written in; a codec stores those whole as its payload.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<SaveFormat>,
+    /// The game's payload, or its base64 ciphertext if `encryption` is set.
+    pub payload: Value,
+}
+impl SaveEnvelope {
+    /// Whether `data` looks like an envelope rather than a plain payload.
+    pub fn is_envelope(data: &Value) -> bool {
+        data.get(ENVELOPE_KEY).is_some_and(Value::is_u64)
+    }
+    /// Whether `data` is a binary save written by [`crate::save::binary::to_save_data`], rather than
+    /// a plain payload that merely looks like an envelope.
+    pub fn is_binary(data: &Value) -> bool {
+        Self::is_envelope(data)
+            && data.get("payload").is_some_and(Value::is_string)
+            && data
+                .get("format")
+                .and_then(|format| SaveFormat::deserialize(format).ok())
+                .is_some_and(|format| format != SaveFormat::Json)
+    }
+}
+
+/// Checksums of an envelope's slot, header and stored payload (the ciphertext, if encrypted).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IntegrityTag {
+    /// Base64 SHA-256 of the bytes from [`integrity_bytes`].
+    pub sha256: String,
+    /// Base64 HMAC-SHA256 of the bytes from [`integrity_bytes`], keyed by a server secret.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hmac: Option<String>,
+}
+
+/// How save payloads are checked for corruption and tampering.
+#[derive(Clone, PartialEq, Eq)]
+pub enum SaveIntegrity {
+    /// Stores a SHA-256 content hash. This only detects accidental corruption: a player who edits
+    /// a save can recompute the SHA-256, so it does not detect tampering.
+    Hash,
+    /// Stores a content hash and an HMAC keyed by a server secret. Saves without a valid HMAC are
+    /// reported as tampered with, including saves that are not wrapped in an envelope at all.
+    #[cfg(feature = "server")]
+    Hmac { secret: Vec<u8> },
+}
+impl std::fmt::Debug for SaveIntegrity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveIntegrity::Hash => write!(f, "Hash"),
+            #[cfg(feature = "server")]
+            SaveIntegrity::Hmac { .. } => write!(f, "Hmac {{ secret: <redacted> }}"),
+        }
+    }
+}
+
+/// Encodes save data on [`crate::Gamplo::save`] and decodes it on [`crate::Gamplo::get_save`].
+///
+/// With nothing configured and no [`SaveHeader`], data is stored as-is. Otherwise it is wrapped in an envelope
+/// (`{"$gamplo": 1, "payload": ..., ...}`) that carries what is needed to check and decode it.
+/// Once integrity checks are configured, saves without an integrity tag, such as saves written
+/// before the codec was configured, fail to decode unless [`SaveCodec::with_legacy_saves`] allows them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveCodec {
+    integrity: Option<SaveIntegrity>,
+    encryption: Option<SaveEncryption>,
+    schemas: BTreeMap<u32, SaveSchema>,
+    default_schema: Option<SaveSchema>,
+    legacy_saves: bool,
+}
+impl SaveCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds an integrity check to stored saves.
+    ///
+    /// The check covers the slot and header along with the payload, so a save copied into another
+    /// slot or given another header fails it. Checked saves must be written to an explicit slot.
+    pub fn with_integrity(mut self, integrity: SaveIntegrity) -> Self {
+        self.integrity = Some(integrity);
+        self
+    }
+    /// Accepts saves written before integrity checks or encryption were configured, which have no
+    /// integrity tag or are not encrypted, and returns them without checking them. Anyone who can
+    /// write a slot can then bypass the checks by removing the tag or storing plaintext, so only
+    /// enable this while migrating existing saves.
+    pub fn with_legacy_saves(mut self, allow: bool) -> Self {
+        self.legacy_saves = allow;
+        self
+    }
+    /// Encrypts stored saves, so their contents can only be read with one of the configured keys.
+    ///
+    /// Ciphertexts are bound to their slot, so encrypted saves must be written to an explicit slot.
+    pub fn with_encryption(mut self, encryption: SaveEncryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+    /// Requires the data saved in `slot` to match `schema`.
+    pub fn with_schema(mut self, slot: u32, schema: SaveSchema) -> Self {
+        self.schemas.insert(slot, schema);
+        self
+    }
+    /// Requires the data saved in slots without a schema of their own, or without a slot, to match `schema`.
+    pub fn with_default_schema(mut self, schema: SaveSchema) -> Self {
+        self.default_schema = Some(schema);
+        self
+    }
+    /// Returns the schema data in `slot` must match, if any.
+    pub fn schema(&self, slot: Option<u32>) -> Option<&SaveSchema> {
+        slot.and_then(|slot| self.schemas.get(&slot))
+            .or(self.default_schema.as_ref())
+    }
+    /// Checks the game's payload for `slot` against its schema.
+    ///
+    /// Binary payloads (see [`crate::save::binary`]) are opaque and are not checked.
+    pub fn validate(&self, slot: Option<u32>, payload: &Value) -> Result<(), GamploError> {
+        let Some(schema) = self.schema(slot) else {
+            return Ok(());
+        };
+        if SaveEnvelope::is_binary(payload) {
+            return Ok(());
+        }
+        schema
+            .validate(payload)
+            .map_err(|violations| GamploError::SaveValidation { slot, violations })
+    }
+    pub fn integrity(&self) -> Option<&SaveIntegrity> {
+        self.integrity.as_ref()
+    }
+    pub fn encryption(&self) -> Option<&SaveEncryption> {
+        self.encryption.as_ref()
+    }
+    /// Whether this codec changes the stored data at all.
+    pub fn is_identity(&self) -> bool {
+        self.integrity.is_none() && self.encryption.is_none()
+    }
+
+    /// Turns the game's payload for `slot` into the data to store.
+    pub fn encode(&self, slot: Option<u32>, payload: Value) -> Result<Value, GamploError> {
+        self.encode_with_header(slot, None, payload)
+    }
+    /// Turns the game's payload for `slot` and an optional header into the data to store.
+    pub fn encode_with_header(
+        &self,
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        payload: Value,
+    ) -> Result<Value, GamploError> {
+        // Payloads that look like envelopes, such as binary saves from `crate::save::binary`, are
+        // wrapped like any other so they decode back unchanged.
+        if self.is_identity() && header.is_none() && !SaveEnvelope::is_envelope(&payload) {
+            return Ok(payload);
+        }
+        let (encryption, payload) = match &self.encryption {
+            None => (None, payload),
+            Some(encryption) => {
+                let slot = slot.ok_or_else(|| {
+                    GamploError::Encryption(
+                        "Encrypted saves must be written to an explicit slot".to_string(),
+                    )
+                })?;
+                let (tag, ciphertext) = encryption.encrypt(slot, &payload)?;
+                (Some(tag), ciphertext)
+            }
+        };
+        let integrity = match &self.integrity {
+            None => None,
+            Some(integrity) => {
+                let slot = slot.ok_or_else(|| {
+                    GamploError::SaveEncoding(
+                        "Saves with integrity checks must be written to an explicit slot"
+                            .to_string(),
+                    )
+                })?;
+                let bytes = integrity_bytes(slot, header, None, &payload)?;
+                Some(IntegrityTag {
+                    sha256: BASE64.encode(Sha256::digest(&bytes)),
+                    hmac: match integrity {
+                        SaveIntegrity::Hash => None,
+                        #[cfg(feature = "server")]
+                        SaveIntegrity::Hmac { secret } => {
+                            use hmac::Mac;
+                            Some(BASE64.encode(hmac(secret, &bytes)?.finalize().into_bytes()))
+                        }
+                    },
+                })
+            }
+        };
+        Ok(serde_json::to_value(SaveEnvelope {
+            version: ENVELOPE_VERSION,
+            header: header.cloned(),
+            integrity,
+            encryption,
+            format: None,
+            payload,
+        })?)
+    }
+    /// Turns stored data back into the game's payload, checking it along the way.
+    pub fn decode(&self, slot: u32, data: Value) -> Result<Value, GamploError> {
+        Ok(self.decode_with_header(slot, data)?.1)
+    }
+    /// Turns stored data back into its header, if it has one, and the game's payload.
+    pub fn decode_with_header(
+        &self,
+        slot: u32,
+        data: Value,
+    ) -> Result<(Option<SaveHeader>, Value), GamploError> {
+        if !SaveEnvelope::is_envelope(&data) {
+            self.check_missing_integrity(slot)?;
+            self.check_missing_encryption(slot)?;
+            return Ok((None, data));
+        }
+        let envelope: SaveEnvelope =
+            serde_json::from_value(data).map_err(|e| GamploError::SaveCorrupted {
+                slot,
+                reason: format!("invalid save envelope: {}", e),
+            })?;
+        if envelope.version > ENVELOPE_VERSION {
+            return Err(GamploError::SaveCorrupted {
+                slot,
+                reason: format!(
+                    "save envelope version {} is newer than this SDK supports",
+                    envelope.version
+                ),
+            });
+        }
+        self.check_integrity(slot, &envelope)?;
+        let payload = match (&envelope.encryption, &self.encryption) {
+            (None, _) => {
+                self.check_missing_encryption(slot)?;
+                envelope.payload
+            }
+            (Some(tag), Some(encryption)) => encryption.decrypt(slot, tag, &envelope.payload)?,
+            (Some(tag), None) => {
+                return Err(GamploError::SaveDecryption {
+                    slot,
+                    reason: format!("no key configured for key {}", tag.key_id),
+                });
+            }
+        };
+        let payload = match envelope.format {
+            None => payload,
+            // A binary save stored without a codec envelope of its own: hand it back as saved.
+            format => serde_json::to_value(SaveEnvelope {
+                version: ENVELOPE_VERSION,
+                format,
+                payload,
+                ..Default::default()
+            })?,
+        };
+        Ok((envelope.header, payload))
+    }
+
+    fn check_integrity(&self, slot: u32, envelope: &SaveEnvelope) -> Result<(), GamploError> {
+        let Some(tag) = &envelope.integrity else {
+            return self.check_missing_integrity(slot);
+        };
+        let bytes = integrity_bytes(
+            slot,
+            envelope.header.as_ref(),
+            envelope.format,
+            &envelope.payload,
+        )?;
+        if BASE64.encode(Sha256::digest(&bytes)) != tag.sha256 {
+            return Err(GamploError::SaveCorrupted {
+                slot,
+                reason: "content hash does not match".to_string(),
+            });
+        }
+        #[cfg(feature = "server")]
+        if let Some(SaveIntegrity::Hmac { secret }) = &self.integrity {
+            use hmac::Mac;
+            let expected = tag
+                .hmac
+                .as_ref()
+                .and_then(|hmac| BASE64.decode(hmac).ok())
+                .ok_or(GamploError::SaveTampered { slot })?;
+            hmac(secret, &bytes)?
+                .verify_slice(&expected)
+                .map_err(|_| GamploError::SaveTampered { slot })?;
+        }
+        Ok(())
+    }
+    /// Fails on a save without an integrity tag when integrity checks are required.
+    fn check_missing_integrity(&self, slot: u32) -> Result<(), GamploError> {
+        match &self.integrity {
+            _ if self.legacy_saves => Ok(()),
+            None => Ok(()),
+            Some(SaveIntegrity::Hash) => Err(GamploError::SaveCorrupted {
+                slot,
+                reason: "save has no integrity tag".to_string(),
+            }),
+            #[cfg(feature = "server")]
+            Some(SaveIntegrity::Hmac { .. }) => Err(GamploError::SaveTampered { slot }),
+        }
+    }
+    /// Fails on a plaintext save when encryption is configured, so saves can't be downgraded.
+    fn check_missing_encryption(&self, slot: u32) -> Result<(), GamploError> {
+        match self.encryption {
+            Some(_) if !self.legacy_saves => Err(GamploError::SaveDecryption {
+                slot,
+                reason: "save is not encrypted".to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The bytes an integrity tag covers: the slot the save is stored in, and the envelope's header,
+/// format tag and stored payload, as a JSON array.
+fn integrity_bytes(
+    slot: u32,
+    header: Option<&SaveHeader>,
+    format: Option<SaveFormat>,
+    payload: &Value,
+) -> Result<Vec<u8>, GamploError> {
+    Ok(serde_json::to_vec(&(slot, header, format, payload))?)
+}
+
+/// Starts an HMAC-SHA256 over `bytes` keyed by `secret`.
+#[cfg(feature = "server")]
+fn hmac(secret: &[u8], bytes: &[u8]) -> Result<hmac::Hmac<Sha256>, GamploError> {
+    use hmac::Mac;
+    let mut mac = hmac::Hmac::<Sha256>::new_from_slice(secret)
+        .map_err(|e| GamploError::ApiError(format!("Invalid HMAC secret: {}", e)))?;
+    mac.update(bytes);
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn identity_codec_passes_through() {
+        let codec = SaveCodec::new();
+        let data = json!({"level": 3});
+        assert_eq!(codec.encode(None, data.clone()).unwrap(), data);
+        assert_eq!(codec.decode(1, data.clone()).unwrap(), data);
+    }
+
+    #[test]
+    fn detects_corruption() {
+        let codec = SaveCodec::new().with_integrity(SaveIntegrity::Hash);
+        let payload = json!({"level": 3, "gold": 100});
+        let mut stored = codec.encode(Some(1), payload.clone()).unwrap();
+        assert_eq!(stored[ENVELOPE_KEY], json!(1));
+        assert_eq!(codec.decode(1, stored.clone()).unwrap(), payload);
+        // Plain data, e.g. written before the codec was configured, only loads when allowed.
+        assert!(matches!(
+            codec.decode(1, payload.clone()),
+            Err(GamploError::SaveCorrupted { slot: 1, .. })
+        ));
+        let legacy = codec.clone().with_legacy_saves(true);
+        assert_eq!(legacy.decode(1, payload.clone()).unwrap(), payload);
+
+        let mut stripped = stored.clone();
+        stripped.as_object_mut().unwrap().remove("integrity");
+        assert!(matches!(
+            codec.decode(1, stripped),
+            Err(GamploError::SaveCorrupted { slot: 1, .. })
+        ));
+
+        // The hash covers the slot and the header too.
+        assert!(matches!(
+            codec.decode(2, stored.clone()),
+            Err(GamploError::SaveCorrupted { slot: 2, .. })
+        ));
+        let mut renamed = stored.clone();
+        renamed["header"] = serde_json::to_value(SaveHeader::named("Hero")).unwrap();
+        assert!(matches!(
+            codec.decode(1, renamed),
+            Err(GamploError::SaveCorrupted { slot: 1, .. })
+        ));
+        assert!(codec.encode(None, payload.clone()).is_err());
+
+        stored["payload"]["gold"] = json!(999999);
+        assert!(matches!(
+            codec.decode(1, stored),
+            Err(GamploError::SaveCorrupted { slot: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn encrypts_payload() {
+        let codec = SaveCodec::new()
+            .with_integrity(SaveIntegrity::Hash)
+            .with_encryption(SaveEncryption::new("v1", [7; 32]));
+        let payload = json!({"secret": "treasure is under the tree"});
+        let stored = codec.encode(Some(1), payload.clone()).unwrap();
+        assert!(!stored.to_string().contains("treasure"));
+        assert_eq!(stored["encryption"]["keyId"], json!("v1"));
+        assert_eq!(codec.decode(1, stored.clone()).unwrap(), payload);
+
+        // Copying the ciphertext into another slot is detected, by the hash and by the encryption itself.
+        assert!(matches!(
+            codec.decode(2, stored.clone()),
+            Err(GamploError::SaveCorrupted { slot: 2, .. })
+        ));
+        let unchecked = SaveCodec::new().with_encryption(SaveEncryption::new("v1", [7; 32]));
+        let copied = unchecked.encode(Some(1), payload.clone()).unwrap();
+        assert!(matches!(
+            unchecked.decode(2, copied),
+            Err(GamploError::SaveDecryption { slot: 2, .. })
+        ));
+        // A codec without the key cannot read it.
+        assert!(matches!(
+            SaveCodec::new().decode(1, stored),
+            Err(GamploError::SaveDecryption { slot: 1, .. })
+        ));
+        assert!(codec.encode(None, payload.clone()).is_err());
+
+        // Plaintext saves, even with a valid hash, are only accepted as legacy saves.
+        let plaintext = SaveCodec::new()
+            .with_integrity(SaveIntegrity::Hash)
+            .encode(Some(1), payload.clone())
+            .unwrap();
+        assert!(matches!(
+            codec.decode(1, plaintext.clone()),
+            Err(GamploError::SaveDecryption { slot: 1, .. })
+        ));
+        let legacy = codec.with_legacy_saves(true);
+        assert_eq!(legacy.decode(1, plaintext).unwrap(), payload);
+    }
+
+    #[test]
+    fn keeps_envelope_like_payloads_intact() {
+        let header = SaveHeader::named("Hero");
+        let game = json!({"$gamplo": 7, "payload": "the game's own key"});
+        let checked = SaveCodec::new().with_integrity(SaveIntegrity::Hash);
+        for codec in [SaveCodec::new(), checked] {
+            let stored = codec.encode(Some(1), game.clone()).unwrap();
+            assert_eq!(codec.decode(1, stored).unwrap(), game);
+            let stored = codec
+                .encode_with_header(Some(1), Some(&header), game.clone())
+                .unwrap();
+            assert_eq!(
+                codec.decode_with_header(1, stored).unwrap(),
+                (Some(header.clone()), game.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn validates_against_slot_schema() {
+        let codec = SaveCodec::new()
+            .with_schema(1, SaveSchema::new(json!({"type": "object"})).unwrap())
+            .with_default_schema(SaveSchema::new(json!({"type": "string"})).unwrap());
+        assert!(codec.validate(Some(1), &json!({"level": 3})).is_ok());
+        assert!(codec.validate(Some(2), &json!("notes")).is_ok());
+        assert!(codec.validate(None, &json!("notes")).is_ok());
+        let Err(GamploError::SaveValidation { slot, violations }) =
+            codec.validate(Some(1), &json!([1]))
+        else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(slot, Some(1));
+        assert_eq!(violations[0].pointer, "");
+        assert!(codec.is_identity());
+
+        // Game data that merely looks like an envelope is still checked; binary saves are not.
+        assert!(
+            codec
+                .validate(Some(1), &json!({"$gamplo": 7, "payload": "x"}))
+                .is_ok()
+        );
+        assert!(codec.validate(Some(2), &json!({"$gamplo": 7})).is_err());
+        let binary = crate::save::binary::to_save_data(&[1, 2], SaveFormat::MessagePack).unwrap();
+        assert!(codec.validate(Some(2), &binary).is_ok());
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn detects_tampering() {
+        let codec = SaveCodec::new().with_integrity(SaveIntegrity::Hmac {
+            secret: b"server secret".to_vec(),
+        });
+        let payload = json!({"gold": 100});
+        let stored = codec.encode(Some(1), payload.clone()).unwrap();
+        assert_eq!(codec.decode(1, stored.clone()).unwrap(), payload);
+
+        // A validly signed save replayed into another slot is rejected.
+        assert!(matches!(
+            codec.decode(2, stored),
+            Err(GamploError::SaveCorrupted { slot: 2, .. })
+        ));
+
+        // Recomputing the hash is not enough without the secret.
+        let forged = SaveCodec::new()
+            .with_integrity(SaveIntegrity::Hash)
+            .encode(Some(2), json!({"gold": 999999}))
+            .unwrap();
+        assert!(matches!(
+            codec.decode(2, forged),
+            Err(GamploError::SaveTampered { slot: 2 })
+        ));
+        assert!(matches!(
+            codec.decode(2, payload),
+            Err(GamploError::SaveTampered { slot: 2 })
+        ));
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chacha20poly1305::{
+    KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, Payload},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::GamploError;
+
+/// The only algorithm currently written. Stored in the envelope so others can be added later.
+const ALGORITHM: &str = "xchacha20poly1305";
+/// PBKDF2-HMAC-SHA256 rounds used by [`SaveEncryption::from_passphrase`].
+pub const PASSPHRASE_ROUNDS: u32 = 100_000;
+
+/// How an encrypted payload was encrypted. The key itself is never stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EncryptionTag {
+    pub alg: String,
+    pub key_id: String,
+    /// Base64 nonce.
+    pub nonce: String,
+}
+
+/// Keys used to encrypt save payloads with XChaCha20-Poly1305.
+///
+/// New saves are encrypted with the active key. Every key's id is stored next to the ciphertext,
+/// so keys can be rotated by making a new key active while keeping the old ones for decryption.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SaveEncryption {
+    active: String,
+    keys: HashMap<String, [u8; 32]>,
+}
+impl std::fmt::Debug for SaveEncryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut key_ids: Vec<_> = self.keys.keys().collect();
+        key_ids.sort();
+        f.debug_struct("SaveEncryption")
+            .field("active", &self.active)
+            .field("key_ids", &key_ids)
+            .finish_non_exhaustive()
+    }
+}
+impl SaveEncryption {
+    /// Encrypts with the 256-bit `key`, identified by `key_id`.
+    pub fn new(key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        let active = key_id.into();
+        Self {
+            keys: HashMap::from([(active.clone(), key)]),
+            active,
+        }
+    }
+    /// Encrypts with a key derived from `passphrase` and `salt` using PBKDF2-HMAC-SHA256.
+    ///
+    /// The salt should be unique per game (e.g. the game's id), and the same salt must be used to decrypt.
+    pub fn from_passphrase(key_id: impl Into<String>, passphrase: &str, salt: &[u8]) -> Self {
+        Self::new(key_id, derive_key(passphrase, salt))
+    }
+    /// Adds a key that is only used to decrypt saves written with it, e.g. a key that was rotated out.
+    pub fn with_key(mut self, key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        self.keys.entry(key_id.into()).or_insert(key);
+        self
+    }
+    /// Adds a passphrase-derived key that is only used to decrypt saves written with it.
+    pub fn with_passphrase(self, key_id: impl Into<String>, passphrase: &str, salt: &[u8]) -> Self {
+        self.with_key(key_id, derive_key(passphrase, salt))
+    }
+    /// The id of the key new saves are encrypted with.
+    pub fn active_key_id(&self) -> &str {
+        &self.active
+    }
+
+    /// Encrypts `payload`'s JSON for `slot`, returning the tag and the base64 ciphertext.
+    ///
+    /// The slot is authenticated along with the key id, so the ciphertext fails to decrypt if it is
+    /// copied into another slot.
+    pub(crate) fn encrypt(
+        &self,
+        slot: u32,
+        payload: &Value,
+    ) -> Result<(EncryptionTag, Value), GamploError> {
+        let mut nonce = [0u8; 24];
+        getrandom::getrandom(&mut nonce)
+            .map_err(|e| GamploError::Encryption(format!("Failed to generate nonce: {}", e)))?;
+        let plaintext = serde_json::to_vec(payload)?;
+        let ciphertext = self
+            .cipher(&self.active)?
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &associated_data(&self.active, slot),
+                },
+            )
+            .map_err(|_| GamploError::Encryption("Failed to encrypt save data".to_string()))?;
+        let tag = EncryptionTag {
+            alg: ALGORITHM.to_string(),
+            key_id: self.active.clone(),
+            nonce: BASE64.encode(nonce),
+        };
+        Ok((tag, Value::String(BASE64.encode(ciphertext))))
+    }
+    /// Reverses [`SaveEncryption::encrypt`].
+    pub(crate) fn decrypt(
+        &self,
+        slot: u32,
+        tag: &EncryptionTag,
+        payload: &Value,
+    ) -> Result<Value, GamploError> {
+        let error = |reason: &str| GamploError::SaveDecryption {
+            slot,
+            reason: reason.to_string(),
+        };
+        if tag.alg != ALGORITHM {
+            return Err(error(&format!("unsupported algorithm {}", tag.alg)));
+        }
+        let nonce = BASE64
+            .decode(&tag.nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == 24)
+            .ok_or_else(|| error("invalid nonce"))?;
+        let ciphertext = payload
+            .as_str()
+            .and_then(|text| BASE64.decode(text).ok())
+            .ok_or_else(|| error("payload is not base64 ciphertext"))?;
+        let plaintext = self
+            .cipher(&tag.key_id)
+            .map_err(|_| error(&format!("unknown key {}", tag.key_id)))?
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &associated_data(&tag.key_id, slot),
+                },
+            )
+            .map_err(|_| error("wrong key or altered ciphertext"))?;
+        serde_json::from_slice(&plaintext).map_err(|e| error(&e.to_string()))
+    }
+
+    fn cipher(&self, key_id: &str) -> Result<XChaCha20Poly1305, GamploError> {
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| GamploError::Encryption(format!("Unknown key {}", key_id)))?;
+        Ok(XChaCha20Poly1305::new(key.into()))
+    }
+}
+
+/// The data authenticated alongside a payload: its key id and slot.
+fn associated_data(key_id: &str, slot: u32) -> Vec<u8> {
+    format!("{}\0{}", key_id, slot).into_bytes()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    pbkdf2::pbkdf2_hmac_array::<sha2::Sha256, 32>(passphrase.as_bytes(), salt, PASSPHRASE_ROUNDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn round_trip_and_rotation() {
+        let old = SaveEncryption::new("v1", [1; 32]);
+        let payload = json!({"secret": "treasure is under the tree"});
+        let (tag, ciphertext) = old.encrypt(1, &payload).unwrap();
+        assert_eq!(tag.key_id, "v1");
+        assert!(!ciphertext.as_str().unwrap().contains("treasure"));
+        assert_eq!(old.decrypt(1, &tag, &ciphertext).unwrap(), payload);
+
+        let rotated = SaveEncryption::new("v2", [2; 32]).with_key("v1", [1; 32]);
+        assert_eq!(rotated.decrypt(1, &tag, &ciphertext).unwrap(), payload);
+        assert_eq!(rotated.encrypt(1, &payload).unwrap().0.key_id, "v2");
+        // The ciphertext only decrypts in the slot it was written to.
+        assert!(old.decrypt(2, &tag, &ciphertext).is_err());
+
+        let wrong = SaveEncryption::new("v1", [3; 32]);
+        assert!(matches!(
+            wrong.decrypt(1, &tag, &ciphertext),
+            Err(GamploError::SaveDecryption { slot: 1, .. })
+        ));
+        assert!(
+            SaveEncryption::new("v2", [2; 32])
+                .decrypt(1, &tag, &ciphertext)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn passphrase_keys_are_deterministic() {
+        let a = SaveEncryption::from_passphrase("p", "correct horse", b"my-game");
+        let b = SaveEncryption::from_passphrase("p", "correct horse", b"my-game");
+        assert_eq!(a, b);
+        assert!(!format!("{:?}", a).contains("keys:"));
+    }
+}
@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::GamploBackend,
+    error::GamploError,
+    save::{SaveMetadata, get_saves_concurrently},
+    storage::{MemoryStorage, Storage},
+};
+
+/// What a save-select screen shows about a slot, written with
+/// [`GamploBackend::save_with_header`] and returned in [`crate::save::SaveData::header`].
+///
+/// Headers are stored next to the payload and are neither encrypted nor covered by the
+/// integrity check of a [`crate::save::codec::SaveCodec`], so they should not contain anything secret.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveHeader {
+    /// The name of the save, e.g. the name the player gave their character.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Where the player is, e.g. "Chapter 3" or "World 2-1".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chapter: Option<String>,
+    /// Total play time in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playtime_seconds: Option<u64>,
+    /// A small image as a `data:` URL, usable directly as an `<img>` source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+    /// The version of the game that wrote the save.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_version: Option<String>,
+}
+impl SaveHeader {
+    /// Creates a header with just a display name.
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Default::default()
+        }
+    }
+    pub fn playtime(&self) -> Option<Duration> {
+        self.playtime_seconds.map(Duration::from_secs)
+    }
+    /// Sets the thumbnail to `bytes` of the image type `content_type`, e.g. `image/png`.
+    pub fn with_thumbnail(mut self, content_type: &str, bytes: &[u8]) -> Self {
+        self.thumbnail = Some(format!(
+            "data:{};base64,{}",
+            content_type,
+            BASE64.encode(bytes)
+        ));
+        self
+    }
+    /// Decodes the thumbnail into its content type and bytes.
+    pub fn thumbnail_bytes(&self) -> Option<(&str, Vec<u8>)> {
+        let (content_type, data) = self
+            .thumbnail
+            .as_deref()?
+            .strip_prefix("data:")?
+            .split_once(";base64,")?;
+        Some((content_type, BASE64.decode(data).ok()?))
+    }
+}
+
+/// A slot as shown on a save-select screen: its metadata and, if it was saved with one, its header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SaveSummary {
+    pub metadata: SaveMetadata,
+    pub header: Option<SaveHeader>,
+}
+
+/// A header as kept in [`Storage`], along with the version of the slot it was read from.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredHeader {
+    updated_at: DateTime<Utc>,
+    header: Option<SaveHeader>,
+}
+
+/// Lists save slots with their headers.
+///
+/// The API has no way to read a header without its save, so headers are cached by the slot's
+/// `updated_at`, and only slots that changed since the last listing are downloaded again.
+#[derive(Debug, Default)]
+pub struct SaveHeaderCache<S = MemoryStorage> {
+    storage: S,
+}
+impl SaveHeaderCache<MemoryStorage> {
+    /// Creates a cache kept in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl<S: Storage> SaveHeaderCache<S> {
+    /// Creates a cache kept in `storage`, so headers survive restarts.
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+    /// Lists every save slot of `backend` with its header, downloading at most `concurrency` changed slots at a time.
+    ///
+    /// Slots whose save fails to download are listed without a header and retried on the next listing.
+    pub async fn list(
+        &self,
+        backend: &impl GamploBackend,
+        concurrency: usize,
+    ) -> Result<Vec<SaveSummary>, GamploError> {
+        let saves = backend.get_saves().await?;
+        let mut summaries = Vec::new();
+        let mut stale = Vec::new();
+        for metadata in saves.saves {
+            match self.read(metadata.slot)? {
+                Some(stored) if stored.updated_at == metadata.updated_at => {
+                    summaries.push(SaveSummary {
+                        metadata,
+                        header: stored.header,
+                    })
+                }
+                _ => stale.push(metadata),
+            }
+        }
+
+        let mut fetched =
+            get_saves_concurrently(backend, stale.iter().map(|m| m.slot), concurrency).await;
+        for metadata in stale {
+            let header = match fetched.remove(&metadata.slot) {
+                Some(Ok(save)) => {
+                    let stored = StoredHeader {
+                        updated_at: save.updated_at,
+                        header: save.header,
+                    };
+                    self.storage.set(
+                        &storage_key(metadata.slot),
+                        &serde_json::to_string(&stored)?,
+                    )?;
+                    stored.header
+                }
+                _ => None,
+            };
+            summaries.push(SaveSummary { metadata, header });
+        }
+        summaries.sort_by_key(|s| s.metadata.slot);
+        Ok(summaries)
+    }
+    /// Forgets the cached header of `slot`.
+    pub fn invalidate(&self, slot: u32) -> Result<(), GamploError> {
+        self.storage.remove(&storage_key(slot))
+    }
+
+    fn read(&self, slot: u32) -> Result<Option<StoredHeader>, GamploError> {
+        // An unreadable entry is just a cache miss.
+        Ok(self
+            .storage
+            .get(&storage_key(slot))?
+            .and_then(|text| serde_json::from_str(&text).ok()))
+    }
+}
+
+fn storage_key(slot: u32) -> String {
+    format!("save-header:{}", slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{local::LocalBackend, util::block_on};
+
+    #[test]
+    fn thumbnail_round_trip() {
+        let header = SaveHeader::named("Hero").with_thumbnail("image/png", &[1, 2, 3]);
+        assert_eq!(
+            header.thumbnail.as_deref(),
+            Some("data:image/png;base64,AQID")
+        );
+        assert_eq!(header.thumbnail_bytes(), Some(("image/png", vec![1, 2, 3])));
+    }
+
+    #[test]
+    fn lists_headers_and_refreshes_changed_slots() {
+        let backend = LocalBackend::in_memory();
+        let cache = SaveHeaderCache::new();
+        block_on(async {
+            let header = SaveHeader {
+                chapter: Some("Chapter 2".to_string()),
+                playtime_seconds: Some(3600),
+                ..SaveHeader::named("Hero")
+            };
+            backend
+                .save_with_header(Some(1), Some(&header), json!({"hp": 3}))
+                .await
+                .unwrap();
+            backend.save(Some(2), json!("no header")).await.unwrap();
+
+            let list = cache.list(&backend, 2).await.unwrap();
+            assert_eq!(list.len(), 2);
+            assert_eq!(list[0].header.as_ref(), Some(&header));
+            assert_eq!(list[1].header, None);
+            assert_eq!(
+                backend.get_save(1).await.unwrap().unwrap().data,
+                json!({"hp": 3})
+            );
+
+            let renamed = SaveHeader::named("Renamed");
+            backend
+                .save_with_header(Some(1), Some(&renamed), json!({"hp": 2}))
+                .await
+                .unwrap();
+            let list = cache.list(&backend, 2).await.unwrap();
+            assert_eq!(list[0].header.as_ref(), Some(&renamed));
+        });
+    }
+
+    #[test]
+    fn header_less_saves_remove_the_header() {
+        let backend = LocalBackend::in_memory();
+        let header = SaveHeader::named("Hero");
+        block_on(async {
+            backend
+                .save_with_header(Some(1), Some(&header), json!(1))
+                .await
+                .unwrap();
+            backend.save(Some(1), json!(2)).await.unwrap();
+            assert_eq!(backend.get_save(1).await.unwrap().unwrap().header, None);
+
+            crate::save::binary::save_as_with_header(
+                &backend,
+                Some(1),
+                Some(&header),
+                &3,
+                crate::save::binary::SaveFormat::MessagePack,
+            )
+            .await
+            .unwrap();
+            let save = backend.get_save(1).await.unwrap().unwrap();
+            assert_eq!(save.header.as_ref(), Some(&header));
+
+            backend
+                .save_with_header(Some(1), None, json!(4))
+                .await
+                .unwrap();
+            assert_eq!(backend.get_save(1).await.unwrap().unwrap().header, None);
+        });
+    }
+}
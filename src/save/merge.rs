@@ -0,0 +1,443 @@
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+
+use crate::{
+    backend::GamploBackend,
+    error::GamploError,
+    storage::{MemoryStorage, Storage},
+    util::{join_pointer, parse_pointer},
+};
+
+/// How a value changed on both sides since the ancestor is merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MergeRule {
+    /// The larger number, e.g. for high scores.
+    Max,
+    /// The smaller number, e.g. for best times.
+    Min,
+    /// Both sides' changes added up (`local + remote - ancestor`), e.g. for counters.
+    Sum,
+    /// Every item of both arrays, without duplicates, e.g. for unlocked items.
+    Union,
+    /// The value of the side that was saved last, e.g. for settings.
+    LatestWins,
+}
+
+/// Which side of a merge a value comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MergeSide {
+    Local,
+    Remote,
+}
+
+/// A value both sides changed differently without a [`MergeRule`] declared for it.
+/// It was resolved by taking the value of the side that was saved last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// JSON pointer to the value.
+    pub pointer: String,
+    /// `None` where the value does not exist, e.g. because that side deleted it.
+    pub ancestor: Option<Value>,
+    pub local: Option<Value>,
+    pub remote: Option<Value>,
+    /// The side whose value was kept.
+    pub resolved_with: MergeSide,
+}
+
+/// The outcome of a three-way merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeResult {
+    pub merged: Value,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Merges save data that changed on two sides since a common ancestor.
+///
+/// Values only one side changed keep that change. Objects both sides changed are merged key by key,
+/// and arrays both sides changed without changing their length are merged index by index. Other values both sides changed are merged by the [`MergeRule`] declared for their path, or
+/// else by taking the latest side and reporting a [`MergeConflict`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveMerger {
+    rules: Vec<(Vec<String>, MergeRule)>,
+}
+impl SaveMerger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Merges the values at `pointer` with `rule`. A `*` token matches any key or index, e.g.
+    /// `/characters/*/level`. When several rules match, the first one added is used.
+    ///
+    /// Rules below an array index only apply while the array is merged index by index, i.e. while
+    /// neither side changed its length.
+    pub fn with_rule(mut self, pointer: &str, rule: MergeRule) -> Result<Self, GamploError> {
+        self.rules.push((parse_pointer(pointer)?, rule));
+        Ok(self)
+    }
+    /// Merges `local` and `remote`, which both started out as `ancestor`. `latest` is the side saved last.
+    ///
+    /// Without an ancestor, everything both sides have is treated as changed on both sides.
+    pub fn merge(
+        &self,
+        ancestor: Option<&Value>,
+        local: &Value,
+        remote: &Value,
+        latest: MergeSide,
+    ) -> MergeResult {
+        let mut conflicts = Vec::new();
+        let merged = self.merge_value(
+            &mut Vec::new(),
+            ancestor,
+            Some(local),
+            Some(remote),
+            latest,
+            &mut conflicts,
+        );
+        MergeResult {
+            merged: merged.unwrap_or(Value::Null),
+            conflicts,
+        }
+    }
+    /// Merges the local save data of `slot` with the one stored in `backend`, then writes the
+    /// result to `backend` and keeps it in `ancestors` as the ancestor of the next merge.
+    pub async fn sync<S: Storage>(
+        &self,
+        backend: &impl GamploBackend,
+        ancestors: &MergeAncestors<S>,
+        slot: u32,
+        local: Value,
+        local_updated_at: DateTime<Utc>,
+    ) -> Result<MergeResult, GamploError> {
+        let (result, remote) = match backend.get_save(slot).await? {
+            None => (
+                MergeResult {
+                    merged: local,
+                    conflicts: Vec::new(),
+                },
+                None,
+            ),
+            Some(remote) => {
+                let latest = match local_updated_at >= remote.updated_at {
+                    true => MergeSide::Local,
+                    false => MergeSide::Remote,
+                };
+                let ancestor = ancestors.get(slot)?;
+                let result = self.merge(ancestor.as_ref(), &local, &remote.data, latest);
+                (result, Some(remote))
+            }
+        };
+        match remote {
+            Some(remote) if remote.data == result.merged => {}
+            remote => {
+                let header = remote.and_then(|r| r.header);
+                backend
+                    .save_with_header(Some(slot), header.as_ref(), result.merged.clone())
+                    .await?;
+            }
+        }
+        ancestors.set(slot, &result.merged)?;
+        Ok(result)
+    }
+
+    fn rule(&self, path: &[String]) -> Option<MergeRule> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| {
+                pattern.len() == path.len()
+                    && pattern.iter().zip(path).all(|(p, t)| p == "*" || p == t)
+            })
+            .map(|(_, rule)| *rule)
+    }
+    fn merge_value(
+        &self,
+        path: &mut Vec<String>,
+        ancestor: Option<&Value>,
+        local: Option<&Value>,
+        remote: Option<&Value>,
+        latest: MergeSide,
+        conflicts: &mut Vec<MergeConflict>,
+    ) -> Option<Value> {
+        if local == remote || remote == ancestor {
+            return local.cloned();
+        }
+        if local == ancestor {
+            return remote.cloned();
+        }
+        if let Some(rule) = self.rule(path) {
+            return apply_rule(rule, ancestor, local, remote, latest);
+        }
+        if let (Some(Value::Object(local)), Some(Value::Object(remote))) = (local, remote) {
+            let empty = Map::new();
+            let ancestor = ancestor.and_then(Value::as_object).unwrap_or(&empty);
+            let mut merged = Map::new();
+            let keys = local
+                .keys()
+                .chain(remote.keys().filter(|k| !local.contains_key(*k)));
+            for key in keys {
+                path.push(key.clone());
+                let value = self.merge_value(
+                    path,
+                    ancestor.get(key),
+                    local.get(key),
+                    remote.get(key),
+                    latest,
+                    conflicts,
+                );
+                path.pop();
+                if let Some(value) = value {
+                    merged.insert(key.clone(), value);
+                }
+            }
+            return Some(Value::Object(merged));
+        }
+        if let (Some(Value::Array(local)), Some(Value::Array(remote))) = (local, remote)
+            && local.len() == remote.len()
+            && ancestor.is_none_or(|a| a.as_array().is_some_and(|a| a.len() == local.len()))
+        {
+            let merged = (0..local.len())
+                .map(|index| {
+                    path.push(index.to_string());
+                    let value = self.merge_value(
+                        path,
+                        ancestor.and_then(|a| a.get(index)),
+                        local.get(index),
+                        remote.get(index),
+                        latest,
+                        conflicts,
+                    );
+                    path.pop();
+                    value.unwrap_or_default()
+                })
+                .collect();
+            return Some(Value::Array(merged));
+        }
+        conflicts.push(MergeConflict {
+            pointer: join_pointer(path),
+            ancestor: ancestor.cloned(),
+            local: local.cloned(),
+            remote: remote.cloned(),
+            resolved_with: latest,
+        });
+        match latest {
+            MergeSide::Local => local.cloned(),
+            MergeSide::Remote => remote.cloned(),
+        }
+    }
+}
+
+fn apply_rule(
+    rule: MergeRule,
+    ancestor: Option<&Value>,
+    local: Option<&Value>,
+    remote: Option<&Value>,
+    latest: MergeSide,
+) -> Option<Value> {
+    let latest_value = || match latest {
+        MergeSide::Local => local.cloned(),
+        MergeSide::Remote => remote.cloned(),
+    };
+    let number = |value: Option<&Value>| value.and_then(Value::as_f64);
+    match rule {
+        MergeRule::LatestWins => latest_value(),
+        MergeRule::Max | MergeRule::Min => match (number(local), number(remote)) {
+            (Some(l), Some(r)) => {
+                let local_wins = match rule {
+                    MergeRule::Max => l >= r,
+                    _ => l <= r,
+                };
+                if local_wins { local } else { remote }.cloned()
+            }
+            (Some(_), None) => local.cloned(),
+            (None, Some(_)) => remote.cloned(),
+            (None, None) => latest_value(),
+        },
+        MergeRule::Sum => {
+            let (Some(l), Some(r)) = (local, remote) else {
+                return latest_value();
+            };
+            let integer = match (
+                l.as_i64(),
+                r.as_i64(),
+                ancestor.map_or(Some(0), Value::as_i64),
+            ) {
+                (Some(l), Some(r), Some(a)) => {
+                    i64::try_from(l as i128 + r as i128 - a as i128).ok()
+                }
+                _ => None,
+            };
+            // Sums of fractions, or past the range of i64, are added as floats.
+            match (
+                integer,
+                l.as_f64(),
+                r.as_f64(),
+                number(ancestor).or(Some(0.0)),
+            ) {
+                (Some(sum), ..) => Some(Value::from(sum)),
+                (None, Some(l), Some(r), Some(a)) => Some(Value::from(l + r - a)),
+                _ => latest_value(),
+            }
+        }
+        MergeRule::Union => match (local, remote) {
+            (Some(Value::Array(l)), Some(Value::Array(r))) => {
+                let mut union = l.clone();
+                for item in r {
+                    if !union.contains(item) {
+                        union.push(item.clone());
+                    }
+                }
+                Some(Value::Array(union))
+            }
+            (Some(Value::Array(_)), None) => local.cloned(),
+            (None, Some(Value::Array(_))) => remote.cloned(),
+            _ => latest_value(),
+        },
+    }
+}
+
+/// The ancestors of [`SaveMerger::sync`]: the last merged data of each slot, kept in a [`Storage`].
+#[derive(Debug, Default)]
+pub struct MergeAncestors<S = MemoryStorage> {
+    storage: S,
+}
+impl MergeAncestors<MemoryStorage> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl<S: Storage> MergeAncestors<S> {
+    /// Keeps ancestors in `storage`, which should persist across sessions, e.g. a [`crate::storage::LocalStorage`].
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+    pub fn get(&self, slot: u32) -> Result<Option<Value>, GamploError> {
+        self.storage
+            .get(&storage_key(slot))?
+            .map(|text| serde_json::from_str(&text))
+            .transpose()
+            .map_err(Into::into)
+    }
+    pub fn set(&self, slot: u32, data: &Value) -> Result<(), GamploError> {
+        self.storage
+            .set(&storage_key(slot), &serde_json::to_string(data)?)
+    }
+    pub fn remove(&self, slot: u32) -> Result<(), GamploError> {
+        self.storage.remove(&storage_key(slot))
+    }
+}
+
+fn storage_key(slot: u32) -> String {
+    format!("merge-ancestor:{}", slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{local::LocalBackend, util::block_on};
+
+    fn merger() -> SaveMerger {
+        SaveMerger::new()
+            .with_rule("/highScore", MergeRule::Max)
+            .unwrap()
+            .with_rule("/stats/*", MergeRule::Sum)
+            .unwrap()
+            .with_rule("/unlocked", MergeRule::Union)
+            .unwrap()
+            .with_rule("/settings", MergeRule::LatestWins)
+            .unwrap()
+    }
+
+    #[test]
+    fn merges_with_rules_and_reports_conflicts() {
+        let ancestor = json!({
+            "highScore": 100, "stats": {"kills": 10, "deaths": 2}, "unlocked": ["sword"],
+            "settings": {"volume": 1}, "name": "Hero", "level": 3, "gold": 50
+        });
+        let local = json!({
+            "highScore": 150, "stats": {"kills": 15, "deaths": 2}, "unlocked": ["sword", "bow"],
+            "settings": {"volume": 0}, "name": "Hero", "level": 4, "gold": 20
+        });
+        let remote = json!({
+            "highScore": 120, "stats": {"kills": 12, "deaths": 3}, "unlocked": ["sword", "axe"],
+            "settings": {"volume": 2}, "name": "Renamed", "level": 5, "gold": 50
+        });
+        let result = merger().merge(Some(&ancestor), &local, &remote, MergeSide::Remote);
+        assert_eq!(
+            result.merged,
+            json!({
+                "highScore": 150, "stats": {"kills": 17, "deaths": 3},
+                "unlocked": ["sword", "bow", "axe"], "settings": {"volume": 2},
+                "name": "Renamed", "level": 5, "gold": 20
+            })
+        );
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].pointer, "/level");
+        assert_eq!(result.conflicts[0].resolved_with, MergeSide::Remote);
+    }
+
+    #[test]
+    fn merges_arrays_by_index() {
+        let merger = SaveMerger::new()
+            .with_rule("/characters/*/xp", MergeRule::Sum)
+            .unwrap();
+        let ancestor = json!({"characters": [{"xp": 10, "name": "A"}, {"xp": 0}]});
+        let local = json!({"characters": [{"xp": 15, "name": "A"}, {"xp": 1}]});
+        let remote = json!({"characters": [{"xp": 12, "name": "B"}, {"xp": 0}]});
+        let result = merger.merge(Some(&ancestor), &local, &remote, MergeSide::Local);
+        assert_eq!(
+            result.merged["characters"],
+            json!([{"xp": 17, "name": "B"}, {"xp": 1}])
+        );
+        assert!(result.conflicts.is_empty());
+
+        // Arrays whose length changed are not merged by index.
+        let longer = json!({"characters": [{"xp": 10, "name": "A"}, {"xp": 0}, {"xp": 0}]});
+        let result = merger.merge(Some(&ancestor), &local, &longer, MergeSide::Local);
+        assert_eq!(result.conflicts[0].pointer, "/characters");
+
+        // Sums past the range of i64 fall back to floats.
+        let sums = SaveMerger::new().with_rule("/big", MergeRule::Sum).unwrap();
+        let local = json!({"big": i64::MAX});
+        let result = sums.merge(
+            Some(&json!({"big": 0})),
+            &local,
+            &json!({"big": 1}),
+            MergeSide::Local,
+        );
+        assert_eq!(result.merged["big"], json!(i64::MAX as f64 + 1.0));
+    }
+
+    #[test]
+    fn sync_keeps_ancestor() {
+        let backend = LocalBackend::in_memory();
+        let ancestors = MergeAncestors::new();
+        let merger = merger();
+        block_on(async {
+            let first = json!({"unlocked": ["sword"], "highScore": 10});
+            merger
+                .sync(&backend, &ancestors, 1, first.clone(), Utc::now())
+                .await
+                .unwrap();
+            assert_eq!(ancestors.get(1).unwrap(), Some(first));
+
+            // Another device unlocks an item while this one sets a high score.
+            backend
+                .save(
+                    Some(1),
+                    json!({"unlocked": ["sword", "axe"], "highScore": 10}),
+                )
+                .await
+                .unwrap();
+            let local = json!({"unlocked": ["sword"], "highScore": 30});
+            let result = merger
+                .sync(&backend, &ancestors, 1, local, Utc::now())
+                .await
+                .unwrap();
+            let expected = json!({"unlocked": ["sword", "axe"], "highScore": 30});
+            assert_eq!(result.merged, expected);
+            assert!(result.conflicts.is_empty());
+            assert_eq!(backend.get_save(1).await.unwrap().unwrap().data, expected);
+            assert_eq!(ancestors.get(1).unwrap(), Some(expected));
+        });
+    }
+}
@@ -0,0 +1,277 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    backend::GamploBackend,
+    error::GamploError,
+    moderation::{DEFAULT_MODERATION_CONCURRENCY, moderated::MODERATED_MARKER},
+    save::{SaveWriteResponse, header::SaveHeader},
+    util::{join_pointer, parse_pointer},
+};
+
+/// What [`SaveModerator`] does with blocked strings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BlockedTextPolicy {
+    /// Fails with [`GamploError::SaveModeration`] without saving.
+    Reject,
+    /// Replaces each blocked string with this text, e.g. a default name.
+    Replace(String),
+}
+
+/// A string in save data that moderation blocked.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockedText {
+    /// JSON pointer to the string.
+    pub pointer: String,
+    pub text: String,
+    pub reason: Option<String>,
+}
+impl fmt::Display for BlockedText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            Some(reason) => write!(f, "{} ({})", self.pointer, reason),
+            None => write!(f, "{}", self.pointer),
+        }
+    }
+}
+
+/// Moderates user-generated strings in save data, such as character names or sign messages,
+/// before they're saved.
+///
+/// Only strings at the declared paths are moderated. Paths that don't exist or don't hold a
+/// string are skipped. Strings marked as already moderated (see
+/// [`crate::moderation::moderated::Moderated`]) are moderated again, since anyone who can edit
+/// the save can add the marker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveModerator {
+    paths: Vec<Vec<String>>,
+    policy: BlockedTextPolicy,
+    concurrency: usize,
+}
+impl SaveModerator {
+    pub fn new(policy: BlockedTextPolicy) -> Self {
+        Self {
+            paths: Vec::new(),
+            policy,
+            concurrency: DEFAULT_MODERATION_CONCURRENCY,
+        }
+    }
+    /// Moderates the string at `pointer`. A `*` token matches any key or index, e.g.
+    /// `/characters/*/name`, so a key that is literally `*` can't be addressed on its own.
+    pub fn with_path(mut self, pointer: &str) -> Result<Self, GamploError> {
+        self.paths.push(parse_pointer(pointer)?);
+        Ok(self)
+    }
+    /// Sets how many strings are moderated at once.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+    /// Returns every string at the declared paths, by JSON pointer.
+    pub fn strings(&self, data: &Value) -> BTreeMap<String, String> {
+        let mut strings = BTreeMap::new();
+        for path in &self.paths {
+            collect(data, path, &mut Vec::new(), &mut strings);
+        }
+        strings
+    }
+    /// Moderates the strings of `data` with `backend` and applies the policy to blocked ones.
+    ///
+    /// Returns the strings that were blocked, which are already replaced in `data` when the policy
+    /// is [`BlockedTextPolicy::Replace`].
+    pub async fn moderate(
+        &self,
+        backend: &(impl GamploBackend + ?Sized),
+        data: &mut Value,
+    ) -> Result<Vec<BlockedText>, GamploError> {
+        let strings = self.strings(data);
+        let texts: Vec<&str> = strings.values().map(String::as_str).collect();
+        let results = backend.moderate_many(&texts, self.concurrency).await?;
+        let blocked: Vec<BlockedText> = strings
+            .iter()
+            .zip(results)
+            .filter(|(_, result)| result.is_blocked())
+            .map(|((pointer, text), result)| BlockedText {
+                pointer: pointer.clone(),
+                text: text.clone(),
+                reason: result.reason().cloned(),
+            })
+            .collect();
+        match &self.policy {
+            BlockedTextPolicy::Reject if !blocked.is_empty() => {
+                return Err(GamploError::SaveModeration { blocked });
+            }
+            BlockedTextPolicy::Reject => {}
+            BlockedTextPolicy::Replace(replacement) => {
+                for text in &blocked {
+                    if let Some(value) = data.pointer_mut(&text.pointer) {
+                        *value = Value::String(replacement.clone());
+                    }
+                }
+            }
+        }
+        Ok(blocked)
+    }
+    /// Moderates `data` and then writes it to `slot` of `backend`, without a header.
+    pub async fn save(
+        &self,
+        backend: &(impl GamploBackend + ?Sized),
+        slot: Option<u32>,
+        data: Value,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        self.save_with_header(backend, slot, None, data).await
+    }
+    /// Moderates `data` and then writes it to `slot` of `backend` along with `header`.
+    pub async fn save_with_header(
+        &self,
+        backend: &(impl GamploBackend + ?Sized),
+        slot: Option<u32>,
+        header: Option<&SaveHeader>,
+        mut data: Value,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        self.moderate(backend, &mut data).await?;
+        backend.save_with_header(slot, header, data).await
+    }
+    /// Serializes `value` as JSON, moderates it and then writes it to `slot` of `backend`.
+    pub async fn save_value<T: Serialize + ?Sized>(
+        &self,
+        backend: &(impl GamploBackend + ?Sized),
+        slot: Option<u32>,
+        value: &T,
+    ) -> Result<SaveWriteResponse, GamploError> {
+        self.save(backend, slot, serde_json::to_value(value)?).await
+    }
+}
+
+fn collect(
+    value: &Value,
+    path: &[String],
+    current: &mut Vec<String>,
+    strings: &mut BTreeMap<String, String>,
+) {
+    let Some((token, rest)) = path.split_first() else {
+        match value {
+            Value::String(text) => {
+                strings.insert(join_pointer(current), text.clone());
+            }
+            // A `Moderated` string: moderate the string inside the marker.
+            Value::Object(map) if map.len() == 1 => {
+                if let Some(Value::String(text)) = map.get(MODERATED_MARKER) {
+                    current.push(MODERATED_MARKER.to_string());
+                    strings.insert(join_pointer(current), text.clone());
+                    current.pop();
+                }
+            }
+            _ => {}
+        }
+        return;
+    };
+    let children: Vec<(String, &Value)> = match (value, token.as_str()) {
+        (Value::Object(map), "*") => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        (Value::Array(items), "*") => items
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        (Value::Object(map), _) => map
+            .get(token)
+            .map(|v| (token.clone(), v))
+            .into_iter()
+            .collect(),
+        (Value::Array(items), _) => token
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| items.get(i))
+            .map(|v| (token.clone(), v))
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    };
+    for (key, child) in children {
+        current.push(key);
+        collect(child, rest, current, strings);
+        current.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{ModerationResult, local::TestBackend, util::block_on};
+
+    /// Blocks any text containing "darn".
+    fn strict() -> TestBackend {
+        TestBackend::new().with_moderation(|text| match text.contains("darn") {
+            true => ModerationResult::Blocked {
+                reason: Some("profanity".to_string()),
+            },
+            false => ModerationResult::Allowed,
+        })
+    }
+
+    fn data() -> Value {
+        json!({
+            "characters": [{ "name": "Jay", "level": 3 }, { "name": "darn it", "level": 1 }],
+            "signs": { "home": "welcome", "cave": "darn cave" },
+            "title": "darn",
+        })
+    }
+
+    #[test]
+    fn replaces_blocked_strings() {
+        let backend = strict();
+        let moderator = SaveModerator::new(BlockedTextPolicy::Replace("***".to_string()))
+            .with_path("/characters/*/name")
+            .unwrap()
+            .with_path("/signs/*")
+            .unwrap()
+            .with_path("/characters/*/level")
+            .unwrap();
+        let mut data = data();
+        let blocked = block_on(moderator.moderate(&backend, &mut data)).unwrap();
+        let pointers: Vec<&str> = blocked.iter().map(|b| b.pointer.as_str()).collect();
+        assert_eq!(pointers, ["/characters/1/name", "/signs/cave"]);
+        assert_eq!(data["characters"][0]["name"], json!("Jay"));
+        assert_eq!(data["characters"][1]["name"], json!("***"));
+        assert_eq!(data["signs"]["cave"], json!("***"));
+        // Only declared paths are moderated.
+        assert_eq!(data["title"], json!("darn"));
+    }
+
+    #[test]
+    fn remoderates_marked_strings() {
+        let backend = strict();
+        let moderator = SaveModerator::new(BlockedTextPolicy::Replace("***".to_string()))
+            .with_path("/name")
+            .unwrap();
+        let mut data = json!({ "name": { "$moderated": "darn" } });
+        let blocked = block_on(moderator.moderate(&backend, &mut data)).unwrap();
+        assert_eq!(blocked[0].pointer, "/name/$moderated");
+        assert_eq!(data, json!({ "name": { "$moderated": "***" } }));
+    }
+
+    #[test]
+    fn rejects_blocked_saves() {
+        let backend = strict();
+        let moderator = SaveModerator::new(BlockedTextPolicy::Reject)
+            .with_path("/title")
+            .unwrap();
+        block_on(async {
+            let err = moderator.save(&backend, Some(1), data()).await.unwrap_err();
+            assert!(
+                matches!(err, GamploError::SaveModeration { ref blocked } if blocked.len() == 1)
+            );
+            assert!(backend.get_save(1).await.unwrap().is_none());
+
+            moderator
+                .save_value(&backend, Some(1), &json!({ "title": "hello" }))
+                .await
+                .unwrap();
+            assert!(backend.get_save(1).await.unwrap().is_some());
+        });
+    }
+}
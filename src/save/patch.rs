@@ -0,0 +1,519 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    backend::GamploBackend,
+    error::GamploError,
+    save::header::SaveHeader,
+    util::{escape_pointer, join_pointer, parse_pointer},
+};
+
+/// Patches kept in a chain before [`IncrementalSave`] compacts them into a new base by default.
+pub const DEFAULT_MAX_PATCHES: usize = 16;
+
+/// One operation of a [`JsonPatch`], as defined by RFC 6902.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+impl std::fmt::Display for PatchOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchOperation::Add { path, value } => write!(f, "add {} = {}", path, value),
+            PatchOperation::Remove { path } => write!(f, "remove {}", path),
+            PatchOperation::Replace { path, value } => write!(f, "replace {} = {}", path, value),
+            PatchOperation::Move { from, path } => write!(f, "move {} -> {}", from, path),
+            PatchOperation::Copy { from, path } => write!(f, "copy {} -> {}", from, path),
+            PatchOperation::Test { path, value } => write!(f, "test {} == {}", path, value),
+        }
+    }
+}
+
+/// A JSON Patch (RFC 6902): a list of operations turning one JSON document into another.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JsonPatch(pub Vec<PatchOperation>);
+impl JsonPatch {
+    /// Computes the patch that turns `old` into `new`.
+    ///
+    /// Objects are compared key by key and arrays index by index, with items added or removed at the end.
+    pub fn diff(old: &Value, new: &Value) -> Self {
+        let mut patch = Self::default();
+        diff(old, new, &mut String::new(), &mut patch.0);
+        patch
+    }
+    /// Applies the patch to `target`. If an operation fails, `target` is left unchanged.
+    pub fn apply(&self, target: &mut Value) -> Result<(), GamploError> {
+        let mut patched = target.clone();
+        for operation in &self.0 {
+            apply(&mut patched, operation).map_err(|reason| {
+                GamploError::PatchApply(format!("({}): {}", operation, reason))
+            })?;
+        }
+        *target = patched;
+        Ok(())
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+/// Lists one operation per line, for debugging.
+impl std::fmt::Display for JsonPatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for operation in &self.0 {
+            writeln!(f, "{}", operation)?;
+        }
+        Ok(())
+    }
+}
+
+fn diff(old: &Value, new: &Value, path: &mut String, patch: &mut Vec<PatchOperation>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let length = path.len();
+                path.push('/');
+                path.push_str(&escape_pointer(key));
+                match new.get(key) {
+                    Some(new_value) => diff(old_value, new_value, path, patch),
+                    None => patch.push(PatchOperation::Remove { path: path.clone() }),
+                }
+                path.truncate(length);
+            }
+            for (key, value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                patch.push(PatchOperation::Add {
+                    path: format!("{}/{}", path, escape_pointer(key)),
+                    value: value.clone(),
+                });
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for (index, (old_item, new_item)) in old.iter().zip(new).enumerate() {
+                let length = path.len();
+                path.push_str(&format!("/{}", index));
+                diff(old_item, new_item, path, patch);
+                path.truncate(length);
+            }
+            // Removing from the back keeps the indices of the remaining removals valid.
+            for index in (new.len()..old.len()).rev() {
+                patch.push(PatchOperation::Remove {
+                    path: format!("{}/{}", path, index),
+                });
+            }
+            for value in new.iter().skip(old.len()) {
+                patch.push(PatchOperation::Add {
+                    path: format!("{}/-", path),
+                    value: value.clone(),
+                });
+            }
+        }
+        (old, new) if old != new => patch.push(PatchOperation::Replace {
+            path: path.clone(),
+            value: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+fn apply(target: &mut Value, operation: &PatchOperation) -> Result<(), String> {
+    match operation {
+        PatchOperation::Add { path, value } => add(target, path, value.clone()),
+        PatchOperation::Remove { path } => remove(target, path).map(drop),
+        PatchOperation::Replace { path, value } => {
+            *get_mut(target, path)? = value.clone();
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            if path.starts_with(&format!("{}/", from)) {
+                return Err("cannot move a value into itself".to_string());
+            }
+            let value = remove(target, from)?;
+            add(target, path, value)
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = get_mut(target, from)?.clone();
+            add(target, path, value)
+        }
+        PatchOperation::Test { path, value } => match get_mut(target, path)? == value {
+            true => Ok(()),
+            false => Err(format!("{} does not equal {}", path, value)),
+        },
+    }
+}
+
+fn get_mut<'a>(target: &'a mut Value, path: &str) -> Result<&'a mut Value, String> {
+    target
+        .pointer_mut(path)
+        .ok_or_else(|| format!("{} does not exist", path))
+}
+/// Splits `path` into the parent value and the last token.
+fn parent<'a>(target: &'a mut Value, path: &str) -> Result<(&'a mut Value, String), String> {
+    let mut tokens = parse_pointer(path).map_err(|e| e.to_string())?;
+    let last = tokens.pop().ok_or("the root has no parent")?;
+    Ok((get_mut(target, &join_pointer(&tokens))?, last))
+}
+fn add(target: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    if path.is_empty() {
+        *target = value;
+        return Ok(());
+    }
+    let (parent, token) = parent(target, path)?;
+    match parent {
+        Value::Object(object) => {
+            object.insert(token, value);
+        }
+        Value::Array(items) => {
+            let index = match token.as_str() {
+                "-" => items.len(),
+                token => array_index(token, items.len() + 1)?,
+            };
+            items.insert(index, value);
+        }
+        _ => return Err(format!("the parent of {} is not a container", path)),
+    }
+    Ok(())
+}
+fn remove(target: &mut Value, path: &str) -> Result<Value, String> {
+    let (parent, token) = parent(target, path)?;
+    match parent {
+        Value::Object(object) => object
+            .remove(&token)
+            .ok_or_else(|| format!("{} does not exist", path)),
+        Value::Array(items) => Ok(items.remove(array_index(&token, items.len())?)),
+        _ => Err(format!("the parent of {} is not a container", path)),
+    }
+}
+fn array_index(token: &str, len: usize) -> Result<usize, String> {
+    // RFC 6901 indices are plain digits without leading zeros.
+    let canonical =
+        token.bytes().all(|b| b.is_ascii_digit()) && (token == "0" || !token.starts_with('0'));
+    Some(token)
+        .filter(|_| canonical)
+        .and_then(|token| token.parse().ok())
+        .filter(|index| *index < len)
+        .ok_or_else(|| format!("{} is not a valid array index", token))
+}
+
+/// What a base slot of an [`IncrementalSave`] holds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Base {
+    #[serde(rename = "$patchBase")]
+    id: u64,
+    data: Value,
+}
+/// What a chain slot of an [`IncrementalSave`] holds: the patches applied on top of the base with `base` as id.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Chain {
+    #[serde(rename = "$patchChain")]
+    base: u64,
+    patches: Vec<JsonPatch>,
+}
+
+/// What [`IncrementalSave::save`] wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchUpload {
+    /// Nothing changed, so nothing was written.
+    Unchanged,
+    /// A patch was appended to the chain slot.
+    Patched { operations: usize, bytes: usize },
+    /// The whole save was written as a new base and the chain was emptied.
+    Compacted { bytes: usize },
+}
+
+/// A save stored as a base snapshot in one slot plus a chain of [`JsonPatch`]es in another.
+///
+/// [`IncrementalSave::save`] only appends the changes since the last save to the chain, which
+/// keeps writes small while the chain is short. Once the chain holds [`IncrementalSave::with_max_patches`]
+/// patches, or grows larger than half the base, it is compacted into a new base.
+/// The base is always written before the chain is reset, so an interrupted compaction never loses data.
+#[derive(Debug)]
+pub struct IncrementalSave<B> {
+    backend: B,
+    base_slot: u32,
+    chain_slot: u32,
+    max_patches: usize,
+    base: Base,
+    chain: Chain,
+    current: Value,
+    header: Option<SaveHeader>,
+}
+impl<B: GamploBackend> IncrementalSave<B> {
+    /// Loads the save kept in `base_slot` and `chain_slot`. Empty slots start out as `null`.
+    pub async fn load(backend: B, base_slot: u32, chain_slot: u32) -> Result<Self, GamploError> {
+        if base_slot == chain_slot {
+            return Err(GamploError::SlotConfig(
+                "the base and chain of an incremental save need different slots".to_string(),
+            ));
+        }
+        let base = match backend.get_save(base_slot).await? {
+            None => Base {
+                id: 0,
+                data: Value::Null,
+            },
+            Some(save) => {
+                serde_json::from_value(save.data).map_err(|_| not_incremental(base_slot))?
+            }
+        };
+        let chain = match backend.get_save(chain_slot).await? {
+            None => Chain::default(),
+            Some(save) => {
+                serde_json::from_value(save.data).map_err(|_| not_incremental(chain_slot))?
+            }
+        };
+        // A chain of another base is left over from an interrupted compaction.
+        let chain = match chain.base == base.id {
+            true => chain,
+            false => Chain {
+                base: base.id,
+                patches: Vec::new(),
+            },
+        };
+        let mut current = base.data.clone();
+        for patch in &chain.patches {
+            patch
+                .apply(&mut current)
+                .map_err(|e| GamploError::SaveCorrupted {
+                    slot: chain_slot,
+                    reason: e.to_string(),
+                })?;
+        }
+        Ok(Self {
+            backend,
+            base_slot,
+            chain_slot,
+            max_patches: DEFAULT_MAX_PATCHES,
+            base,
+            chain,
+            current,
+            header: None,
+        })
+    }
+    /// Sets the header the base slot is written with. See [`IncrementalSave::set_header`].
+    pub fn with_header(mut self, header: SaveHeader) -> Self {
+        self.header = Some(header);
+        self
+    }
+    /// Sets the header the base slot is written with from the next compaction on, or removes it
+    /// with `None`. The chain slot is always written without a header.
+    pub fn set_header(&mut self, header: Option<SaveHeader>) {
+        self.header = header;
+    }
+    /// Sets how many patches the chain holds before it is compacted.
+    pub fn with_max_patches(mut self, max_patches: usize) -> Self {
+        self.max_patches = max_patches;
+        self
+    }
+    /// The data as of the last save.
+    pub fn current(&self) -> &Value {
+        &self.current
+    }
+    /// The number of patches in the chain.
+    pub fn chain_len(&self) -> usize {
+        self.chain.patches.len()
+    }
+    /// Computes what changed between the last save and `data`, without writing anything.
+    pub fn diff(&self, data: &Value) -> JsonPatch {
+        JsonPatch::diff(&self.current, data)
+    }
+    /// Saves `data`, writing only the changes since the last save when possible.
+    pub async fn save(&mut self, data: Value) -> Result<PatchUpload, GamploError> {
+        let patch = self.diff(&data);
+        if patch.is_empty() {
+            return Ok(PatchUpload::Unchanged);
+        }
+        let operations = patch.len();
+        let mut chain = self.chain.clone();
+        chain.patches.push(patch);
+        let chain_bytes = serde_json::to_string(&chain)?.len();
+        let base_bytes = serde_json::to_string(&self.base)?.len();
+        if chain.patches.len() <= self.max_patches && chain_bytes * 2 <= base_bytes {
+            self.write(self.chain_slot, None, &chain).await?;
+            self.chain = chain;
+            self.current = data;
+            return Ok(PatchUpload::Patched {
+                operations,
+                bytes: chain_bytes,
+            });
+        }
+        self.compact_to(data).await
+    }
+    /// Writes the current data as a new base and empties the chain.
+    pub async fn compact(&mut self) -> Result<PatchUpload, GamploError> {
+        self.compact_to(self.current.clone()).await
+    }
+    /// Returns the backend this save is stored in.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    async fn compact_to(&mut self, data: Value) -> Result<PatchUpload, GamploError> {
+        let base = Base {
+            id: self.base.id + 1,
+            data,
+        };
+        let bytes = serde_json::to_string(&base)?.len();
+        self.write(self.base_slot, self.header.as_ref(), &base)
+            .await?;
+        // The stored chain now belongs to the old base and is ignored on load, so start an empty
+        // one even if resetting the stored chain fails.
+        self.chain = Chain {
+            base: base.id,
+            patches: Vec::new(),
+        };
+        self.current = base.data.clone();
+        self.base = base;
+        self.write(self.chain_slot, None, &self.chain).await?;
+        Ok(PatchUpload::Compacted { bytes })
+    }
+    async fn write(
+        &self,
+        slot: u32,
+        header: Option<&SaveHeader>,
+        content: &impl Serialize,
+    ) -> Result<(), GamploError> {
+        self.backend
+            .save_with_header(Some(slot), header, serde_json::to_value(content)?)
+            .await?;
+        Ok(())
+    }
+}
+
+fn not_incremental(slot: u32) -> GamploError {
+    GamploError::SaveCorrupted {
+        slot,
+        reason: "does not contain incremental save data".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        local::{LocalBackend, TestBackend},
+        util::block_on,
+    };
+
+    #[test]
+    fn diff_and_apply() {
+        let old =
+            json!({"hp": 3, "items": ["sword", "shield", "potion"], "a/b": 1, "pos": {"x": 1}});
+        let new = json!({"hp": 2, "items": ["sword"], "pos": {"x": 1, "y": 5}, "flags": [true]});
+        let patch = JsonPatch::diff(&old, &new);
+        assert_eq!(
+            patch.to_string(),
+            "remove /a~1b\nreplace /hp = 2\nremove /items/2\nremove /items/1\nadd /pos/y = 5\nadd /flags = [true]\n"
+        );
+        let mut patched = old.clone();
+        patch.apply(&mut patched).unwrap();
+        assert_eq!(patched, new);
+        assert!(JsonPatch::diff(&new, &new).is_empty());
+
+        let patch: JsonPatch = serde_json::from_value(json!([
+            {"op": "copy", "from": "/pos", "path": "/home"},
+            {"op": "move", "from": "/hp", "path": "/pos/hp"},
+            {"op": "test", "path": "/home/x", "value": 1},
+            {"op": "add", "path": "/items/0", "value": "bow"}
+        ]))
+        .unwrap();
+        patch.apply(&mut patched).unwrap();
+        assert_eq!(patched["pos"]["hp"], json!(2));
+        assert_eq!(patched["items"], json!(["bow", "sword"]));
+
+        for path in ["/items/01", "/items/+1"] {
+            let leading: JsonPatch =
+                serde_json::from_value(json!([{"op": "remove", "path": path}])).unwrap();
+            assert!(leading.apply(&mut patched.clone()).is_err());
+        }
+
+        let failing: JsonPatch =
+            serde_json::from_value(json!([{"op": "remove", "path": "/missing"}])).unwrap();
+        let before = patched.clone();
+        assert!(matches!(
+            failing.apply(&mut patched),
+            Err(GamploError::PatchApply(_))
+        ));
+        assert_eq!(patched, before);
+    }
+
+    #[test]
+    fn patches_then_compacts() {
+        let backend = LocalBackend::in_memory();
+        block_on(async {
+            let mut save = IncrementalSave::load(&backend, 1, 2)
+                .await
+                .unwrap()
+                .with_max_patches(2);
+            let world: Vec<u32> = (0..100).collect();
+            assert!(matches!(
+                save.save(json!({"world": world, "hp": 3})).await.unwrap(),
+                PatchUpload::Compacted { .. }
+            ));
+            assert_eq!(
+                save.save(json!({"world": world, "hp": 3})).await.unwrap(),
+                PatchUpload::Unchanged
+            );
+            for hp in [2, 1] {
+                assert!(matches!(
+                    save.save(json!({"world": world, "hp": hp})).await.unwrap(),
+                    PatchUpload::Patched { operations: 1, .. }
+                ));
+            }
+            let reloaded = IncrementalSave::load(&backend, 1, 2).await.unwrap();
+            assert_eq!(reloaded.current()["hp"], json!(1));
+            assert_eq!(reloaded.chain_len(), 2);
+
+            assert!(matches!(
+                save.save(json!({"world": world, "hp": 0})).await.unwrap(),
+                PatchUpload::Compacted { .. }
+            ));
+            let reloaded = IncrementalSave::load(&backend, 1, 2).await.unwrap();
+            assert_eq!(reloaded.current()["hp"], json!(0));
+            assert_eq!(reloaded.chain_len(), 0);
+
+            assert!(matches!(
+                IncrementalSave::load(&backend, 1, 1).await,
+                Err(GamploError::SlotConfig(_))
+            ));
+            assert!(matches!(
+                IncrementalSave::load(&backend, 2, 1).await,
+                Err(GamploError::SaveCorrupted { slot: 2, .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn failed_chain_reset_keeps_later_saves() {
+        let backend = TestBackend::new();
+        block_on(async {
+            let mut save = IncrementalSave::load(&backend, 1, 2)
+                .await
+                .unwrap()
+                .with_max_patches(1);
+            let world: Vec<u32> = (0..100).collect();
+            save.save(json!({"world": world, "hp": 3})).await.unwrap();
+            save.save(json!({"world": world, "hp": 2})).await.unwrap();
+
+            // The new base is written, but resetting the chain fails.
+            backend.fail_writes(2);
+            assert!(save.save(json!({"world": world, "hp": 1})).await.is_err());
+            backend.heal(2);
+            assert!(matches!(
+                save.save(json!({"world": world, "hp": 0})).await.unwrap(),
+                PatchUpload::Patched { .. }
+            ));
+            let reloaded = IncrementalSave::load(&backend, 1, 2).await.unwrap();
+            assert_eq!(reloaded.current()["hp"], json!(0));
+        });
+    }
+}
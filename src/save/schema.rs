@@ -0,0 +1,737 @@
+use std::collections::BTreeMap;
+
+use regex_lite::Regex;
+use serde_json::{Map, Value};
+
+use crate::{error::GamploError, util::escape_pointer};
+
+/// Keywords that only describe a schema and have nothing to check.
+const ANNOTATIONS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$comment",
+    "$defs",
+    "definitions",
+    "title",
+    "description",
+    "default",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+    "format",
+];
+/// Keywords whose value is a single subschema.
+const SUBSCHEMA_KEYWORDS: &[&str] = &["additionalProperties", "items", "not", "if", "then", "else"];
+/// Keywords whose value is a list of subschemas.
+const SUBSCHEMA_LIST_KEYWORDS: &[&str] = &["prefixItems", "allOf", "anyOf", "oneOf"];
+/// Keywords whose value maps names to subschemas.
+const SUBSCHEMA_MAP_KEYWORDS: &[&str] =
+    &["properties", "patternProperties", "$defs", "definitions"];
+/// Keywords checked by [`SaveSchema::validate`] that hold no subschemas.
+const ASSERTIONS: &[&str] = &[
+    "$ref",
+    "type",
+    "enum",
+    "const",
+    "required",
+    "dependentRequired",
+    "minProperties",
+    "maxProperties",
+    "minItems",
+    "maxItems",
+    "uniqueItems",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "multipleOf",
+];
+/// How many `$ref`s may be followed without reaching deeper into the data, to stop `$ref` cycles.
+const MAX_REF_DEPTH: usize = 64;
+
+/// A place where save data does not match its schema.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SchemaViolation {
+    /// JSON pointer (RFC 6901) to the offending value, `""` for the whole save.
+    pub pointer: String,
+    pub message: String,
+}
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pointer = match self.pointer.as_str() {
+            "" => "/",
+            pointer => pointer,
+        };
+        write!(f, "{}: {}", pointer, self.message)
+    }
+}
+
+/// A JSON Schema that save data must match, see [`crate::save::codec::SaveCodec::with_schema`].
+///
+/// Supports the keywords needed to describe save data: `type`, `enum`, `const`, `properties`,
+/// `patternProperties`, `additionalProperties`, `required`, `dependentRequired`, `minProperties`,
+/// `maxProperties`, `items`, `prefixItems`, `minItems`, `maxItems`, `uniqueItems`, `minLength`,
+/// `maxLength`, `pattern`, `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum`,
+/// `multipleOf`, `allOf`, `anyOf`, `oneOf`, `not`, `if`/`then`/`else` and local `$ref`s
+/// (`#/$defs/...`, `#/definitions/...`). Patterns use [`regex_lite`] syntax.
+///
+/// Annotations such as `title`, `default` and `format` are allowed but not checked, as in JSON
+/// Schema 2020-12. Any other keyword makes [`SaveSchema::new`] fail, so a schema never passes
+/// data it was meant to reject.
+#[derive(Debug, Clone)]
+pub struct SaveSchema {
+    root: Value,
+    patterns: BTreeMap<String, Regex>,
+}
+impl PartialEq for SaveSchema {
+    fn eq(&self, other: &Self) -> bool {
+        self.root == other.root
+    }
+}
+impl Eq for SaveSchema {}
+impl SaveSchema {
+    /// Uses `schema`, which must be a JSON Schema object or a boolean.
+    ///
+    /// Fails with [`GamploError::InvalidSchema`] if it uses a keyword that isn't supported, a
+    /// `$ref` that doesn't resolve or a pattern that doesn't compile.
+    pub fn new(schema: Value) -> Result<Self, GamploError> {
+        let mut patterns = BTreeMap::new();
+        compile(&schema, &schema, "", &mut patterns)?;
+        Ok(Self {
+            root: schema,
+            patterns,
+        })
+    }
+    /// Derives the schema from a Rust type.
+    #[cfg(feature = "schemars")]
+    pub fn for_type<T: schemars::JsonSchema>() -> Result<Self, GamploError> {
+        Self::new(schemars::schema_for!(T).to_value())
+    }
+    pub fn as_value(&self) -> &Value {
+        &self.root
+    }
+    /// Checks `data` against the schema, returning every violation found.
+    pub fn validate(&self, data: &Value) -> Result<(), Vec<SchemaViolation>> {
+        let mut violations = Vec::new();
+        self.check(&self.root, data, &mut String::new(), &mut violations, 0);
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(violations),
+        }
+    }
+
+    fn check(
+        &self,
+        schema: &Value,
+        data: &Value,
+        pointer: &mut String,
+        violations: &mut Vec<SchemaViolation>,
+        refs: usize,
+    ) {
+        if refs > MAX_REF_DEPTH {
+            return violations.push(violation(pointer, "$refs nest too deeply"));
+        }
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                return violations.push(violation(pointer, "no value is allowed here"));
+            }
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(target) => self.check(target, data, pointer, violations, refs + 1),
+                None => violations.push(violation(
+                    pointer,
+                    format!("unresolvable $ref {}", reference),
+                )),
+            }
+        }
+        for keyword in ["allOf", "anyOf", "oneOf"] {
+            let Some(options) = schema.get(keyword).and_then(Value::as_array) else {
+                continue;
+            };
+            let results: Vec<Vec<SchemaViolation>> = options
+                .iter()
+                .map(|option| {
+                    let mut found = Vec::new();
+                    self.check(option, data, pointer, &mut found, refs);
+                    found
+                })
+                .collect();
+            let matching = results.iter().filter(|found| found.is_empty()).count();
+            match keyword {
+                "allOf" => violations.extend(results.into_iter().flatten()),
+                "anyOf" if matching == 0 => violations.push(violation(
+                    pointer,
+                    "does not match any of the allowed schemas",
+                )),
+                "oneOf" if matching != 1 => violations.push(violation(
+                    pointer,
+                    format!("matches {} schemas instead of exactly one", matching),
+                )),
+                _ => {}
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            let mut found = Vec::new();
+            self.check(not, data, pointer, &mut found, refs);
+            if found.is_empty() {
+                violations.push(violation(pointer, "matches a schema it must not match"));
+            }
+        }
+        if let Some(condition) = schema.get("if") {
+            let mut found = Vec::new();
+            self.check(condition, data, pointer, &mut found, refs);
+            let branch = match found.is_empty() {
+                true => schema.get("then"),
+                false => schema.get("else"),
+            };
+            if let Some(branch) = branch {
+                self.check(branch, data, pointer, violations, refs);
+            }
+        }
+
+        if let Some(types) = schema.get("type") {
+            let allowed: Vec<&str> = match types {
+                Value::String(name) => vec![name.as_str()],
+                Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !allowed.is_empty() && !allowed.iter().any(|name| has_type(data, name)) {
+                return violations.push(violation(
+                    pointer,
+                    format!(
+                        "expected {}, found {}",
+                        allowed.join(" or "),
+                        type_name(data)
+                    ),
+                ));
+            }
+        }
+        if let Some(options) = schema.get("enum").and_then(Value::as_array)
+            && !options.contains(data)
+        {
+            violations.push(violation(
+                pointer,
+                format!("{} is not one of the allowed values", data),
+            ));
+        }
+        if let Some(expected) = schema.get("const")
+            && expected != data
+        {
+            violations.push(violation(
+                pointer,
+                format!("expected {}, found {}", expected, data),
+            ));
+        }
+
+        match data {
+            Value::String(text) => {
+                let length = text.chars().count() as f64;
+                if let Some(min) = number(schema, "minLength")
+                    && length < min
+                {
+                    violations.push(violation(
+                        pointer,
+                        format!("is shorter than {} characters", min),
+                    ));
+                }
+                if let Some(max) = number(schema, "maxLength")
+                    && length > max
+                {
+                    violations.push(violation(
+                        pointer,
+                        format!("is longer than {} characters", max),
+                    ));
+                }
+                if let Some(pattern) = schema.get("pattern").and_then(Value::as_str)
+                    && let Some(regex) = self.regex(pattern)
+                    && !regex.is_match(text)
+                {
+                    violations.push(violation(
+                        pointer,
+                        format!("does not match the pattern {}", pattern),
+                    ));
+                }
+            }
+            Value::Number(value) => {
+                let value = value.as_f64().unwrap_or_default();
+                if let Some(min) = number(schema, "minimum")
+                    && value < min
+                {
+                    violations.push(violation(pointer, format!("is less than {}", min)));
+                }
+                if let Some(max) = number(schema, "maximum")
+                    && value > max
+                {
+                    violations.push(violation(pointer, format!("is greater than {}", max)));
+                }
+                if let Some(min) = number(schema, "exclusiveMinimum")
+                    && value <= min
+                {
+                    violations.push(violation(pointer, format!("is not greater than {}", min)));
+                }
+                if let Some(max) = number(schema, "exclusiveMaximum")
+                    && value >= max
+                {
+                    violations.push(violation(pointer, format!("is not less than {}", max)));
+                }
+                if let Some(divisor) = number(schema, "multipleOf")
+                    && !is_multiple(value, divisor)
+                {
+                    violations.push(violation(
+                        pointer,
+                        format!("is not a multiple of {}", divisor),
+                    ));
+                }
+            }
+            Value::Array(items) => self.check_array(schema, items, pointer, violations),
+            Value::Object(object) => self.check_object(schema, object, pointer, violations),
+            Value::Null | Value::Bool(_) => {}
+        }
+    }
+    fn check_array(
+        &self,
+        schema: &Map<String, Value>,
+        items: &[Value],
+        pointer: &mut String,
+        violations: &mut Vec<SchemaViolation>,
+    ) {
+        let length = items.len() as f64;
+        if let Some(min) = number(schema, "minItems")
+            && length < min
+        {
+            violations.push(violation(pointer, format!("has fewer than {} items", min)));
+        }
+        if let Some(max) = number(schema, "maxItems")
+            && length > max
+        {
+            violations.push(violation(pointer, format!("has more than {} items", max)));
+        }
+        if schema.get("uniqueItems").and_then(Value::as_bool) == Some(true)
+            && let Some(index) =
+                (1..items.len()).find(|&index| items[..index].contains(&items[index]))
+        {
+            violations.push(violation(
+                pointer,
+                format!("has a duplicate item at index {}", index),
+            ));
+        }
+        let prefix = schema
+            .get("prefixItems")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for (index, item) in items.iter().enumerate() {
+            let item_schema = match prefix.get(index) {
+                Some(schema) => schema,
+                None => match schema.get("items") {
+                    Some(schema) => schema,
+                    None => continue,
+                },
+            };
+            let length = pointer.len();
+            pointer.push_str(&format!("/{}", index));
+            self.check(item_schema, item, pointer, violations, 0);
+            pointer.truncate(length);
+        }
+    }
+    fn check_object(
+        &self,
+        schema: &Map<String, Value>,
+        object: &Map<String, Value>,
+        pointer: &mut String,
+        violations: &mut Vec<SchemaViolation>,
+    ) {
+        let length = object.len() as f64;
+        if let Some(min) = number(schema, "minProperties")
+            && length < min
+        {
+            violations.push(violation(
+                pointer,
+                format!("has fewer than {} properties", min),
+            ));
+        }
+        if let Some(max) = number(schema, "maxProperties")
+            && length > max
+        {
+            violations.push(violation(
+                pointer,
+                format!("has more than {} properties", max),
+            ));
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let pattern_properties = schema.get("patternProperties").and_then(Value::as_object);
+        for name in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(name) {
+                violations.push(violation(
+                    &format!("{}/{}", pointer, escape_pointer(name)),
+                    "is required",
+                ));
+            }
+        }
+        for (present, dependents) in schema
+            .get("dependentRequired")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter(|(present, _)| object.contains_key(*present))
+        {
+            for name in dependents
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .filter(|name| !object.contains_key(*name))
+            {
+                violations.push(violation(
+                    &format!("{}/{}", pointer, escape_pointer(name)),
+                    format!("is required when {} is present", present),
+                ));
+            }
+        }
+        for (name, value) in object {
+            // A property is checked against its named schema and every pattern it matches, and
+            // only falls back to `additionalProperties` if there are none.
+            let mut property_schemas: Vec<&Value> =
+                properties.and_then(|p| p.get(name)).into_iter().collect();
+            property_schemas.extend(
+                pattern_properties
+                    .into_iter()
+                    .flatten()
+                    .filter(|(pattern, _)| {
+                        self.regex(pattern)
+                            .is_some_and(|regex| regex.is_match(name))
+                    })
+                    .map(|(_, schema)| schema),
+            );
+            if property_schemas.is_empty() {
+                property_schemas.extend(schema.get("additionalProperties"));
+            }
+            let length = pointer.len();
+            pointer.push('/');
+            pointer.push_str(&escape_pointer(name));
+            for property_schema in property_schemas {
+                self.check(property_schema, value, pointer, violations, 0);
+            }
+            pointer.truncate(length);
+        }
+    }
+    fn resolve(&self, reference: &str) -> Option<&Value> {
+        resolve(&self.root, reference)
+    }
+    fn regex(&self, pattern: &str) -> Option<&Regex> {
+        self.patterns.get(pattern)
+    }
+}
+
+/// Checks that `schema` only uses supported keywords, and compiles its patterns into `patterns`.
+fn compile(
+    root: &Value,
+    schema: &Value,
+    pointer: &str,
+    patterns: &mut BTreeMap<String, Regex>,
+) -> Result<(), GamploError> {
+    let invalid = |message: String| {
+        let at = match pointer {
+            "" => "/",
+            pointer => pointer,
+        };
+        Err(GamploError::InvalidSchema(format!("{} at {}", message, at)))
+    };
+    let schema = match schema {
+        Value::Bool(_) => return Ok(()),
+        Value::Object(schema) => schema,
+        other => return invalid(format!("expected an object or a boolean, found {}", other)),
+    };
+    for (keyword, value) in schema {
+        let keyword = keyword.as_str();
+        let at = format!("{}/{}", pointer, escape_pointer(keyword));
+        if SUBSCHEMA_KEYWORDS.contains(&keyword) {
+            compile(root, value, &at, patterns)?;
+        } else if SUBSCHEMA_LIST_KEYWORDS.contains(&keyword) {
+            let Some(options) = value.as_array() else {
+                return invalid(format!("`{}` must be a list of schemas", keyword));
+            };
+            for (index, option) in options.iter().enumerate() {
+                compile(root, option, &format!("{}/{}", at, index), patterns)?;
+            }
+        } else if SUBSCHEMA_MAP_KEYWORDS.contains(&keyword) {
+            let Some(schemas) = value.as_object() else {
+                return invalid(format!("`{}` must map names to schemas", keyword));
+            };
+            for (name, subschema) in schemas {
+                if keyword == "patternProperties" {
+                    add_pattern(name, patterns)?;
+                }
+                compile(
+                    root,
+                    subschema,
+                    &format!("{}/{}", at, escape_pointer(name)),
+                    patterns,
+                )?;
+            }
+        } else if keyword == "$ref" {
+            let reference = value.as_str().unwrap_or_default();
+            if resolve(root, reference).is_none() {
+                return invalid(format!("unresolvable $ref {}", value));
+            }
+        } else if keyword == "pattern" {
+            let Some(pattern) = value.as_str() else {
+                return invalid("`pattern` must be a string".to_string());
+            };
+            add_pattern(pattern, patterns)?;
+        } else if !ASSERTIONS.contains(&keyword) && !ANNOTATIONS.contains(&keyword) {
+            return invalid(format!("unsupported keyword `{}`", keyword));
+        }
+    }
+    Ok(())
+}
+fn add_pattern(pattern: &str, patterns: &mut BTreeMap<String, Regex>) -> Result<(), GamploError> {
+    if !patterns.contains_key(pattern) {
+        let regex = Regex::new(pattern)
+            .map_err(|e| GamploError::InvalidSchema(format!("pattern `{}`: {}", pattern, e)))?;
+        patterns.insert(pattern.to_string(), regex);
+    }
+    Ok(())
+}
+fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let path = reference.strip_prefix('#')?;
+    if path.is_empty() {
+        return Some(root);
+    }
+    root.pointer(path)
+}
+
+fn has_type(data: &Value, name: &str) -> bool {
+    match name {
+        "null" => data.is_null(),
+        "boolean" => data.is_boolean(),
+        "string" => data.is_string(),
+        "number" => data.is_number(),
+        "integer" => {
+            data.is_i64() || data.is_u64() || data.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        "array" => data.is_array(),
+        "object" => data.is_object(),
+        _ => false,
+    }
+}
+fn type_name(data: &Value) -> &'static str {
+    match data {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+fn is_multiple(value: f64, divisor: f64) -> bool {
+    let quotient = value / divisor;
+    // Allow for rounding, so 0.3 is a multiple of 0.1.
+    (quotient - quotient.round()).abs() <= 1e-9 * quotient.abs().max(1.0)
+}
+fn number(schema: &Map<String, Value>, keyword: &str) -> Option<f64> {
+    schema.get(keyword).and_then(Value::as_f64)
+}
+fn violation(pointer: &str, message: impl Into<String>) -> SchemaViolation {
+    SchemaViolation {
+        pointer: pointer.to_string(),
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn pointers(schema: &SaveSchema, data: Value) -> Vec<String> {
+        schema
+            .validate(&data)
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|v| v.pointer)
+            .collect()
+    }
+
+    #[test]
+    fn reports_failing_pointers() {
+        let schema = SaveSchema::new(json!({
+            "type": "object",
+            "required": ["level", "inventory"],
+            "properties": {
+                "level": {"type": "integer", "minimum": 1},
+                "inventory": {"type": "array", "items": {"$ref": "#/$defs/item"}},
+                "name": {"type": ["string", "null"], "maxLength": 8}
+            },
+            "additionalProperties": false,
+            "$defs": {
+                "item": {
+                    "type": "object",
+                    "required": ["id"],
+                    "properties": {"id": {"type": "string"}, "a/b": {"const": 1}}
+                }
+            }
+        }))
+        .unwrap();
+        assert!(
+            schema
+                .validate(&json!({"level": 2, "inventory": [{"id": "sword"}], "name": null}))
+                .is_ok()
+        );
+        assert_eq!(
+            pointers(
+                &schema,
+                json!({
+                    "level": 0,
+                    "inventory": [{"id": "sword"}, {"id": 3, "a/b": 2}],
+                    "name": "much too long",
+                    "cheat": true
+                })
+            ),
+            [
+                "/cheat",
+                "/inventory/1/a~1b",
+                "/inventory/1/id",
+                "/level",
+                "/name"
+            ]
+        );
+        assert_eq!(pointers(&schema, json!({"level": 1})), ["/inventory"]);
+        assert_eq!(pointers(&schema, json!([])), [""]);
+    }
+
+    #[test]
+    fn combinators() {
+        let schema = SaveSchema::new(json!({
+            "oneOf": [{"type": "integer"}, {"type": "number", "exclusiveMinimum": 10}],
+            "not": {"const": 3}
+        }))
+        .unwrap();
+        assert!(schema.validate(&json!(2)).is_ok());
+        assert!(schema.validate(&json!(10.5)).is_ok());
+        assert!(schema.validate(&json!(3)).is_err());
+        // 12 is both an integer and a number above 10.
+        assert!(schema.validate(&json!(12)).is_err());
+        assert!(SaveSchema::new(json!("object")).is_err());
+    }
+
+    #[test]
+    fn conditional_and_pattern_keywords() {
+        let schema = SaveSchema::new(json!({
+            "type": "object",
+            "properties": {"name": {"pattern": "^[a-z]+$"}, "tags": {"type": "array"}},
+            "patternProperties": {"^slot_": {"type": "integer", "multipleOf": 5}},
+            "additionalProperties": {"type": "boolean"},
+            "dependentRequired": {"name": ["level"]},
+            "maxProperties": 4,
+            "if": {"required": ["hard"]},
+            "then": {"properties": {"tags": {"uniqueItems": true}}}
+        }))
+        .unwrap();
+        assert!(
+            schema
+                .validate(&json!({"name": "jay", "level": true, "slot_1": 10}))
+                .is_ok()
+        );
+        assert_eq!(
+            pointers(&schema, json!({"name": "Jay", "slot_1": 7, "extra": 1})),
+            ["/level", "/extra", "/name", "/slot_1"]
+        );
+        assert!(schema.validate(&json!({"tags": [1, 1]})).is_ok());
+        assert_eq!(
+            pointers(&schema, json!({"hard": true, "tags": [1, 1]})),
+            ["/tags"]
+        );
+        assert!(
+            schema
+                .validate(&json!({"a": true, "b": true, "c": true, "d": true, "e": true}))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_schemas() {
+        for schema in [
+            json!({"properties": {"name": {"type": "string", "contentSchema": {}}}}),
+            json!({"propertyNames": {"maxLength": 3}}),
+            json!({"items": [{"type": "string"}]}),
+            json!({"$ref": "#/$defs/missing"}),
+            json!({"pattern": "("}),
+        ] {
+            assert!(
+                matches!(
+                    SaveSchema::new(schema.clone()),
+                    Err(GamploError::InvalidSchema(_))
+                ),
+                "{}",
+                schema
+            );
+        }
+        // Annotations are allowed.
+        assert!(SaveSchema::new(json!({"title": "Save", "format": "uint32"})).is_ok());
+    }
+
+    #[test]
+    fn limits_only_ref_depth() {
+        let schema = SaveSchema::new(json!({
+            "$ref": "#/$defs/node",
+            "$defs": {"node": {"type": "object", "properties": {"child": {"$ref": "#/$defs/node"}}}}
+        }))
+        .unwrap();
+        let mut data = json!({});
+        for _ in 0..100 {
+            data = json!({ "child": data });
+        }
+        assert!(schema.validate(&data).is_ok());
+
+        let cycle =
+            SaveSchema::new(json!({"$ref": "#/$defs/a", "$defs": {"a": {"$ref": "#/$defs/a"}}}))
+                .unwrap();
+        assert!(cycle.validate(&json!(1)).is_err());
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn derives_schema_from_type() {
+        #[allow(dead_code)]
+        #[derive(schemars::JsonSchema)]
+        struct Item {
+            id: String,
+        }
+        #[allow(dead_code)]
+        #[derive(schemars::JsonSchema)]
+        struct Save {
+            level: u32,
+            items: Vec<Item>,
+            name: Option<String>,
+        }
+        let schema = SaveSchema::for_type::<Save>().unwrap();
+        assert!(
+            schema
+                .validate(&json!({"level": 1, "items": [{"id": "a"}], "name": null}))
+                .is_ok()
+        );
+        assert_eq!(
+            pointers(&schema, json!({"level": -1, "items": [{"id": 1}]})),
+            ["/items/0/id", "/level"]
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{backend::GamploBackend, error::GamploError, save::header::SaveHeader};
+
+/// One version of a slot's data, tagged with the transaction that wrote it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Version {
+    id: u64,
+    data: Value,
+    /// The header the slot is written with while this version is current.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    header: Option<SaveHeader>,
+}
+
+/// What a data slot holds: the latest written version and the one before it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Record {
+    current: Version,
+    previous: Option<Version>,
+}
+impl Record {
+    fn version(&self, id: u64) -> Option<&Version> {
+        std::iter::once(&self.current)
+            .chain(&self.previous)
+            .find(|v| v.id == id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum SlotContent {
+    #[serde(rename = "$txn")]
+    Record(Box<Record>),
+    #[serde(rename = "$txnCommit")]
+    Marker(Marker),
+}
+
+/// The commit marker: the last committed transaction and the version each slot was committed at.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Marker {
+    id: u64,
+    slots: BTreeMap<u32, u64>,
+}
+
+/// The consistent state of a set of transactional slots, as returned by [`TransactionalSlots::load`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransactionState {
+    /// The data of each slot as of the last committed transaction. Slots that were never committed are absent.
+    pub values: BTreeMap<u32, Value>,
+    /// The id of the last committed transaction, `0` if none.
+    pub transaction_id: u64,
+    /// Slots holding writes from an incomplete transaction, which were rolled back to their committed version.
+    pub rolled_back: Vec<u32>,
+}
+
+/// A set of save slots that are written together, such as world, inventory and settings.
+///
+/// Every data slot keeps its latest and previous version, and a separate marker slot records
+/// which version of each slot belongs to the last committed transaction. The marker is only
+/// written once every data slot was written, so a failure halfway through a
+/// [`Transaction::commit`] leaves the previous consistent set readable by [`TransactionalSlots::load`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TransactionalSlots {
+    marker: u32,
+    slots: Vec<u32>,
+}
+impl TransactionalSlots {
+    /// Creates a set of transactional `slots` whose commit marker is kept in `marker`.
+    pub fn new(marker: u32, slots: impl IntoIterator<Item = u32>) -> Self {
+        Self {
+            marker,
+            slots: slots.into_iter().filter(|s| *s != marker).collect(),
+        }
+    }
+    /// Starts a transaction. Nothing is written until [`Transaction::commit`].
+    pub fn begin(&self) -> Transaction<'_> {
+        Transaction {
+            slots: self,
+            staged: BTreeMap::new(),
+        }
+    }
+    /// Loads the data of every slot as of the last committed transaction.
+    pub async fn load(
+        &self,
+        backend: &impl GamploBackend,
+    ) -> Result<TransactionState, GamploError> {
+        let marker = self.read_marker(backend).await?;
+        let mut state = TransactionState {
+            transaction_id: marker.id,
+            ..Default::default()
+        };
+        for &slot in &self.slots {
+            let record = self.read_record(backend, slot).await?;
+            let committed = marker.slots.get(&slot).copied();
+            match (record, committed) {
+                (Some(record), Some(id)) => {
+                    let version = record.version(id).ok_or_else(|| lost_commit(slot, id))?;
+                    if record.current.id != id {
+                        state.rolled_back.push(slot);
+                    }
+                    state.values.insert(slot, version.data.clone());
+                }
+                (Some(_), None) => state.rolled_back.push(slot),
+                (None, _) => {}
+            }
+        }
+        Ok(state)
+    }
+    /// Loads like [`TransactionalSlots::load`], and rewrites the rolled back slots so they no longer
+    /// hold data from incomplete transactions.
+    pub async fn repair(
+        &self,
+        backend: &impl GamploBackend,
+    ) -> Result<TransactionState, GamploError> {
+        let state = self.load(backend).await?;
+        let marker = self.read_marker(backend).await?;
+        for &slot in &state.rolled_back {
+            match (state.values.get(&slot), marker.slots.get(&slot)) {
+                (Some(_), Some(&id)) => {
+                    let current = self
+                        .read_record(backend, slot)
+                        .await?
+                        .and_then(|record| record.version(id).cloned())
+                        .ok_or_else(|| lost_commit(slot, id))?;
+                    let record = Record {
+                        current,
+                        previous: None,
+                    };
+                    write(backend, slot, SlotContent::Record(Box::new(record))).await?;
+                }
+                _ => {
+                    backend.delete_save(slot).await?;
+                }
+            }
+        }
+        Ok(state)
+    }
+
+    async fn read_marker(&self, backend: &impl GamploBackend) -> Result<Marker, GamploError> {
+        match read(backend, self.marker).await? {
+            None => Ok(Marker::default()),
+            Some(SlotContent::Marker(marker)) => Ok(marker),
+            Some(SlotContent::Record(_)) => Err(not_transactional(self.marker)),
+        }
+    }
+    async fn read_record(
+        &self,
+        backend: &impl GamploBackend,
+        slot: u32,
+    ) -> Result<Option<Record>, GamploError> {
+        match read(backend, slot).await? {
+            None => Ok(None),
+            Some(SlotContent::Record(record)) => Ok(Some(*record)),
+            Some(SlotContent::Marker(_)) => Err(not_transactional(slot)),
+        }
+    }
+}
+
+/// Writes staged for several slots of a [`TransactionalSlots`], committed all at once.
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    slots: &'a TransactionalSlots,
+    staged: BTreeMap<u32, (Option<SaveHeader>, Value)>,
+}
+impl Transaction<'_> {
+    /// Stages `data` to be written to `slot` without a header, replacing anything staged for it before.
+    pub fn stage(&mut self, slot: u32, data: Value) -> Result<(), GamploError> {
+        self.stage_with_header(slot, None, data)
+    }
+    /// Stages `data` to be written to `slot` along with `header`, replacing anything staged for it before.
+    pub fn stage_with_header(
+        &mut self,
+        slot: u32,
+        header: Option<SaveHeader>,
+        data: Value,
+    ) -> Result<(), GamploError> {
+        if !self.slots.slots.contains(&slot) {
+            return Err(GamploError::SlotConfig(format!(
+                "slot {} is not part of this transactional set",
+                slot
+            )));
+        }
+        self.staged.insert(slot, (header, data));
+        Ok(())
+    }
+    /// Writes every staged slot, then the commit marker. Returns the id of the committed transaction.
+    ///
+    /// If this fails, the slots keep reading as they were before the transaction.
+    pub async fn commit(self, backend: &impl GamploBackend) -> Result<u64, GamploError> {
+        let mut marker = self.slots.read_marker(backend).await?;
+        // Each slot's committed version is pinned in the marker, so leftovers of incomplete
+        // transactions that reused this id are never mistaken for committed data.
+        let id = marker.id + 1;
+
+        for (slot, (header, data)) in self.staged {
+            let previous = match (
+                self.slots.read_record(backend, slot).await?,
+                marker.slots.get(&slot),
+            ) {
+                (Some(record), Some(committed)) => record.version(*committed).cloned(),
+                _ => None,
+            };
+            let record = Record {
+                current: Version { id, data, header },
+                previous,
+            };
+            write(backend, slot, SlotContent::Record(Box::new(record))).await?;
+            marker.slots.insert(slot, id);
+        }
+        marker.id = id;
+        write(backend, self.slots.marker, SlotContent::Marker(marker)).await?;
+        Ok(id)
+    }
+}
+
+async fn read(backend: &impl GamploBackend, slot: u32) -> Result<Option<SlotContent>, GamploError> {
+    let Some(save) = backend.get_save(slot).await? else {
+        return Ok(None);
+    };
+    serde_json::from_value(save.data)
+        .map(Some)
+        .map_err(|_| not_transactional(slot))
+}
+
+async fn write(
+    backend: &impl GamploBackend,
+    slot: u32,
+    content: SlotContent,
+) -> Result<(), GamploError> {
+    let header = match &content {
+        SlotContent::Record(record) => record.current.header.as_ref(),
+        SlotContent::Marker(_) => None,
+    };
+    backend
+        .save_with_header(Some(slot), header, serde_json::to_value(&content)?)
+        .await?;
+    Ok(())
+}
+
+fn lost_commit(slot: u32, id: u64) -> GamploError {
+    GamploError::SaveCorrupted {
+        slot,
+        reason: format!("lost the data of committed transaction {}", id),
+    }
+}
+
+fn not_transactional(slot: u32) -> GamploError {
+    GamploError::SaveCorrupted {
+        slot,
+        reason: "does not contain transactional save data".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{local::LocalBackend, util::block_on};
+
+    #[test]
+    fn commits_and_rolls_back() {
+        let backend = LocalBackend::in_memory().with_limits(4, 1024);
+        let slots = TransactionalSlots::new(4, [1, 2, 3]);
+        block_on(async {
+            let mut tx = slots.begin();
+            tx.stage_with_header(1, Some(SaveHeader::named("Hero")), json!("world 1"))
+                .unwrap();
+            tx.stage(2, json!("inventory 1")).unwrap();
+            assert!(matches!(
+                tx.stage(4, json!("marker")),
+                Err(GamploError::SlotConfig(_))
+            ));
+            assert_eq!(tx.commit(&backend).await.unwrap(), 1);
+
+            // Simulate a commit that wrote slot 1 and then failed before writing slot 2 and the marker.
+            let record = Record {
+                current: Version {
+                    id: 2,
+                    data: json!("world 2"),
+                    header: None,
+                },
+                previous: Some(Version {
+                    id: 1,
+                    data: json!("world 1"),
+                    header: Some(SaveHeader::named("Hero")),
+                }),
+            };
+            write(&backend, 1, SlotContent::Record(Box::new(record)))
+                .await
+                .unwrap();
+
+            let state = slots.load(&backend).await.unwrap();
+            assert_eq!(state.transaction_id, 1);
+            assert_eq!(state.rolled_back, [1]);
+            assert_eq!(state.values[&1], json!("world 1"));
+            assert_eq!(state.values[&2], json!("inventory 1"));
+            assert!(!state.values.contains_key(&3));
+
+            // The next transaction reuses the id of the incomplete one without picking up its data.
+            let mut tx = slots.begin();
+            tx.stage(2, json!("inventory 2")).unwrap();
+            assert_eq!(tx.commit(&backend).await.unwrap(), 2);
+            let state = slots.load(&backend).await.unwrap();
+            assert_eq!(state.values[&1], json!("world 1"));
+            assert_eq!(state.values[&2], json!("inventory 2"));
+
+            let repaired = slots.repair(&backend).await.unwrap();
+            assert_eq!(repaired.rolled_back, [1]);
+            assert!(slots.load(&backend).await.unwrap().rolled_back.is_empty());
+            let save = backend.get_save(1).await.unwrap().unwrap();
+            assert_eq!(save.header, Some(SaveHeader::named("Hero")));
+        });
+    }
+}
@@ -0,0 +1,211 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::error::GamploError;
+
+/// Persistent key-value storage used by the SDK to keep data between sessions (e.g. cached achievement icons).
+///
+/// Values are plain strings so they fit in `localStorage`; callers serialize anything else themselves.
+pub trait Storage {
+    /// Gets the value stored under `key`, if any.
+    fn get(&self, key: &str) -> Result<Option<String>, GamploError>;
+    /// Stores `value` under `key`, replacing any previous value.
+    fn set(&self, key: &str, value: &str) -> Result<(), GamploError>;
+    /// Removes the value stored under `key`. Removing a missing key is not an error.
+    fn remove(&self, key: &str) -> Result<(), GamploError>;
+}
+impl<S: Storage + ?Sized> Storage for &S {
+    fn get(&self, key: &str) -> Result<Option<String>, GamploError> {
+        (**self).get(key)
+    }
+    fn set(&self, key: &str, value: &str) -> Result<(), GamploError> {
+        (**self).set(key, value)
+    }
+    fn remove(&self, key: &str) -> Result<(), GamploError> {
+        (**self).remove(key)
+    }
+}
+impl<S: Storage + ?Sized> Storage for std::sync::Arc<S> {
+    fn get(&self, key: &str) -> Result<Option<String>, GamploError> {
+        (**self).get(key)
+    }
+    fn set(&self, key: &str, value: &str) -> Result<(), GamploError> {
+        (**self).set(key, value)
+    }
+    fn remove(&self, key: &str) -> Result<(), GamploError> {
+        (**self).remove(key)
+    }
+}
+
+/// [`Storage`] that keeps everything in memory. Nothing survives a restart.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    entries: Mutex<HashMap<String, String>>,
+}
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl Storage for MemoryStorage {
+    fn get(&self, key: &str) -> Result<Option<String>, GamploError> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+    fn set(&self, key: &str, value: &str) -> Result<(), GamploError> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+    fn remove(&self, key: &str) -> Result<(), GamploError> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// [`Storage`] backed by the browser's `localStorage`. Every key is prefixed with `prefix`.
+#[cfg(feature = "client")]
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    prefix: String,
+}
+#[cfg(feature = "client")]
+impl LocalStorage {
+    /// Creates a `localStorage` backed storage using the `"gamplo:"` key prefix.
+    pub fn new() -> Self {
+        Self::with_prefix("gamplo:")
+    }
+    /// Creates a `localStorage` backed storage that prefixes every key with `prefix`.
+    pub fn with_prefix(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+}
+#[cfg(feature = "client")]
+impl Default for LocalStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[cfg(feature = "client")]
+impl Storage for LocalStorage {
+    fn get(&self, key: &str) -> Result<Option<String>, GamploError> {
+        use gloo_storage::Storage as _;
+        gloo_storage::LocalStorage::raw()
+            .get_item(&self.key(key))
+            .map_err(|e| GamploError::Storage(format!("Failed to read {}: {:?}", key, e)))
+    }
+    fn set(&self, key: &str, value: &str) -> Result<(), GamploError> {
+        use gloo_storage::Storage as _;
+        gloo_storage::LocalStorage::raw()
+            .set_item(&self.key(key), value)
+            .map_err(|e| GamploError::Storage(format!("Failed to write {}: {:?}", key, e)))
+    }
+    fn remove(&self, key: &str) -> Result<(), GamploError> {
+        use gloo_storage::Storage as _;
+        gloo_storage::LocalStorage::raw()
+            .remove_item(&self.key(key))
+            .map_err(|e| GamploError::Storage(format!("Failed to remove {}: {:?}", key, e)))
+    }
+}
+
+/// The longest file name [`FileStorage`] uses, below the 255 bytes most file systems allow.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_FILE_NAME_LEN: usize = 200;
+
+/// [`Storage`] that keeps each key in its own file inside a directory. Not available on wasm.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    dir: std::path::PathBuf,
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    /// Creates a file storage in `dir`. The directory is created on the first write.
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+    /// Keys are percent-encoded so any key maps to a valid, unique file name. Names too long for
+    /// most file systems are replaced by `~` and the key's SHA-256, which no encoded key starts with.
+    fn path(&self, key: &str) -> std::path::PathBuf {
+        let mut name = String::with_capacity(key.len());
+        for byte in key.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+                name.push(byte as char);
+            } else {
+                name.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        if name.len() > MAX_FILE_NAME_LEN {
+            use sha2::{Digest, Sha256};
+            name = Sha256::digest(key.as_bytes())
+                .iter()
+                .fold(String::from("~"), |name, byte| {
+                    name + &format!("{:02x}", byte)
+                });
+        }
+        self.dir.join(name)
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FileStorage {
+    fn get(&self, key: &str) -> Result<Option<String>, GamploError> {
+        match std::fs::read_to_string(self.path(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(GamploError::Storage(format!(
+                "Failed to read {}: {}",
+                key, e
+            ))),
+        }
+    }
+    fn set(&self, key: &str, value: &str) -> Result<(), GamploError> {
+        let path = self.path(key);
+        // Write to a temporary file first so a crash never leaves a half-written value.
+        let temp = path.with_extension("tmp");
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&temp, value))
+            .and_then(|_| std::fs::rename(&temp, &path))
+            .map_err(|e| GamploError::Storage(format!("Failed to write {}: {}", key, e)))
+    }
+    fn remove(&self, key: &str) -> Result<(), GamploError> {
+        match std::fs::remove_file(self.path(key)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(GamploError::Storage(format!(
+                "Failed to remove {}: {}",
+                key, e
+            ))),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_storage() {
+        let dir = std::env::temp_dir().join(format!("gamplo-storage-{}", std::process::id()));
+        let storage = FileStorage::new(&dir);
+        assert_eq!(storage.get("local:save:1").unwrap(), None);
+        storage.set("local:save:1", "{}").unwrap();
+        assert_eq!(storage.get("local:save:1").unwrap().as_deref(), Some("{}"));
+        assert!(dir.join("local%3Asave%3A1").exists());
+        storage.remove("local:save:1").unwrap();
+        storage.remove("local:save:1").unwrap();
+        assert_eq!(storage.get("local:save:1").unwrap(), None);
+
+        // Keys too long for a file name are hashed.
+        let long = format!("icon:https://gamplo.com/{}", "%".repeat(300));
+        storage.set(&long, "long").unwrap();
+        storage.set(&format!("{}/", long), "longer").unwrap();
+        assert_eq!(storage.get(&long).unwrap().as_deref(), Some("long"));
+        assert!(storage.path(&long).file_name().unwrap().len() <= MAX_FILE_NAME_LEN);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,3 +1,5 @@
+use serde::Deserialize;
+
 pub fn get_error(val: &serde_json::Value) -> Option<String> {
     if let Some(error) = val.get("error") {
         if let Some(error_str) = error.as_str() {
@@ -5,4 +7,55 @@ pub fn get_error(val: &serde_json::Value) -> Option<String> {
         }
     }
     None
-}
\ No newline at end of file
+}
+
+/// Deserializes `null` as the type's default value, for fields the API sometimes returns as `null`.
+pub(crate) fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + serde::Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Escapes a key for use as a JSON pointer (RFC 6901) token.
+pub(crate) fn escape_pointer(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Joins unescaped tokens into a JSON pointer (RFC 6901), the reverse of [`parse_pointer`].
+pub(crate) fn join_pointer(tokens: &[String]) -> String {
+    tokens
+        .iter()
+        .map(|token| format!("/{}", escape_pointer(token)))
+        .collect()
+}
+
+/// Splits a JSON pointer (RFC 6901) into its unescaped tokens. `""` points at the whole document.
+pub(crate) fn parse_pointer(pointer: &str) -> Result<Vec<String>, crate::error::GamploError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let tokens = pointer.strip_prefix('/').ok_or_else(|| {
+        crate::error::GamploError::ApiError(format!(
+            "JSON pointer {} does not start with /",
+            pointer
+        ))
+    })?;
+    Ok(tokens
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// Drives a future to completion on the current thread. Only used in tests, where backends resolve without real I/O.
+#[cfg(test)]
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+    loop {
+        if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}